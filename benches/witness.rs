@@ -0,0 +1,49 @@
+use ark_bn254::Fr;
+use criterion::{criterion_group, criterion_main, Criterion};
+use grapevine_sonobe::{
+    circom::{grapevine_circuit, grapevine_native_circuit},
+    utils::{
+        inputs::{get_z0, prepare_external_inputs, random_f_bigint, CircomPrivateInput},
+        path::PathTree,
+    },
+};
+use sonobe::frontend::FCircuit;
+use std::path::PathBuf;
+
+/// Inputs of alice proving degree 1
+fn degree_1_inputs() -> CircomPrivateInput {
+    let path = PathTree::new()
+        .insert(&String::from("alice"), Fr::from(1))
+        .unwrap();
+    CircomPrivateInput {
+        phrase: Some(String::from("This is a secret")),
+        usernames: [None, Some(String::from("alice"))],
+        relationship_secret: None,
+        auth_secret: Some(random_f_bigint::<Fr>()),
+        path: Some(path),
+        chaff: false,
+    }
+}
+
+/// Benchmarks computing one step's witness through one circuit frontend
+fn bench_frontend<FC: FCircuit<Fr>>(c: &mut Criterion, name: &str, f_circuit: FC) {
+    let external_inputs = prepare_external_inputs::<Fr>(&degree_1_inputs()).unwrap();
+    let z_0 = get_z0().to_vec();
+    c.bench_function(&format!("{}_step_native", name), |b| {
+        b.iter(|| {
+            f_circuit
+                .step_native(0, z_0.clone(), external_inputs.clone())
+                .unwrap()
+        })
+    });
+}
+
+fn witness_frontends(c: &mut Criterion) {
+    let r1cs = PathBuf::from("./circom/artifacts/grapevine.r1cs");
+    let wasm = PathBuf::from("./circom/artifacts/grapevine.wasm");
+    bench_frontend(c, "wasm", grapevine_circuit(r1cs.clone(), wasm).unwrap());
+    bench_frontend(c, "native", grapevine_native_circuit(r1cs).unwrap());
+}
+
+criterion_group!(benches, witness_frontends);
+criterion_main!(benches);
@@ -0,0 +1,24 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    // the checked in include/grapevine.h is only regenerated on request, so regular builds
+    // neither write into the source tree nor depend on cbindgen parsing the crate
+    #[cfg(feature = "header")]
+    generate_header();
+}
+
+/** Regenerates the C header of the cdylib from src/ffi.rs */
+#[cfg(feature = "header")]
+fn generate_header() {
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let config = cbindgen::Config::from_file("cbindgen.toml").unwrap();
+    cbindgen::Builder::new()
+        .with_crate(crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("Unable to generate grapevine.h")
+        .write_to_file("include/grapevine.h");
+}
@@ -8,14 +8,15 @@ fi
 # install dependencies
 yarn
 
-# compile circuit
+# compile circuits
+for circuit in grapevine grapevine_eddsa grapevine_anonymous; do
+    circom $circuit.circom \
+        --r1cs \
+        --wasm \
+        --prime bn128 \
+        --output ./artifacts
 
-circom grapevine.circom \
-    --r1cs \
-    --wasm \
-    --prime bn128 \
-    --output ./artifacts
-
-# cleanup
-mv ./artifacts/grapevine_js/grapevine.wasm ./artifacts
-rm -rf ./artifacts/grapevine_js
+    # cleanup
+    mv ./artifacts/${circuit}_js/$circuit.wasm ./artifacts
+    rm -rf ./artifacts/${circuit}_js
+done
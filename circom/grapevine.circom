@@ -5,20 +5,25 @@ include "node_modules/circomlib/circuits/mux1.circom";
 include "node_modules/circomlib/circuits/comparators.circom";
 include "node_modules/circomlib/circuits/gates.circom";
 include "./templates/chaff.circom";
+include "./templates/canonical.circom";
+include "./templates/domains.circom";
+include "./templates/path_tree.circom";
 
-template grapevine(num_felts) {  
+template grapevine(num_felts, path_depth) {  
 
     // in_out schema
     // 0: degrees of separation
     // 1: secret hash from previous step
     // 2: hash of username + secret hash from previous step
     // 3: chaff
+    // 4: running hash of the salted path leaves of the users on the path
+    // 5: root of the merkle tree of salted usernames on the path
 
-    signal input ivc_input[4];
-    signal output ivc_output[4];
+    signal input ivc_input[6];
+    signal output ivc_output[6];
 
     // external inputs at each folding step
-    signal input external_inputs[num_felts+2+2];
+    signal input external_inputs[num_felts+2+2+1+path_depth];
     signal phrase[num_felts]; // secret phrase, if first iteration
     for (var i=0; i<num_felts; i++) {
         phrase[i] <== external_inputs[i];
@@ -26,24 +31,63 @@ template grapevine(num_felts) {
     signal usernames[2]; // prev username, current username
     usernames[0]<==external_inputs[num_felts];
     usernames[1]<==external_inputs[num_felts+1];
-    signal auth_secrets[2]; // prev degree's user secret, current degree's user secret
-    auth_secrets[0]<==external_inputs[num_felts+2];
-    auth_secrets[1]<==external_inputs[num_felts+3];
+    // prev degree's relationship secret H(prev auth secret, phrase hash), shared by the prev user
+    signal prev_relationship_secret <== external_inputs[num_felts+2];
+    // current degree's user secret, never shared
+    signal auth_secret <== external_inputs[num_felts+3];
+    // salt of the current username's leaf in the path tree
+    signal path_salt <== external_inputs[num_felts+4];
+    // siblings of the current username's slot in the path tree
+    signal path_siblings[path_depth];
+    for (var i=0; i<path_depth; i++) {
+        path_siblings[i] <== external_inputs[num_felts+4+1+i];
+    }
+
+    // phrase chunks and usernames must be canonical 31 byte encodings, checked for chaff too
+    component canonical = CanonicalFelts(num_felts + 2);
+    for (var i=0; i<num_felts; i++) {
+        canonical.in[i] <== phrase[i];
+    }
+    canonical.in[num_felts] <== usernames[0];
+    canonical.in[num_felts+1] <== usernames[1];
 
     // name inputs from step_in
     signal degrees_of_separation <== ivc_input[0];
     signal given_phrase_hash <== ivc_input[1];
     signal given_degree_secret_hash <== ivc_input[2];
     signal is_chaff_step <== ivc_input[3];
+    signal given_path_accumulator <== ivc_input[4];
+    signal given_path_root <== ivc_input[5];
 
     // determine whether degrees of separation from secret is zero
     component is_degree_zero = IsZero();
     is_degree_zero.in <== degrees_of_separation;
 
+    // sonobe derives the r1cs by synthesizing a step over an all-zero state and external inputs,
+    // which must have a witness. that step is told apart by its zero path root, as chains start
+    // from the empty tree root (pinned by the verifier) and later roots are poseidon outputs
+    component is_shape_step = IsZero();
+    is_shape_step.in <== given_path_root;
+    signal is_logic_step <== (1 - is_chaff_step) * (1 - is_shape_step.out);
+
+    // the previous username is 0 exactly at degree 0, and logic steps need a current username
+    // (chaff steps are exempt as their usernames are random)
+    usernames[0] * is_degree_zero.out === 0;
+    signal is_logic_past_degree_zero <== (1 - is_degree_zero.out) * is_logic_step;
+    component is_prev_username_zero = IsZero();
+    is_prev_username_zero.in <== usernames[0];
+    is_prev_username_zero.out * is_logic_past_degree_zero === 0;
+    component is_username_zero = IsZero();
+    is_username_zero.in <== usernames[1];
+    is_username_zero.out * is_logic_step === 0;
+
     // compute poseidon hash of secret
     // same as the word essentially
-    component phrase_hasher = Poseidon(num_felts);
-    phrase_hasher.inputs <== phrase;
+    component phrase_hasher = Poseidon(num_felts + 1);
+    phrase_hasher.inputs[0] <== PHRASE_DOMAIN();
+    for (var i=0; i<num_felts; i++) {
+        phrase_hasher.inputs[i+1] <== phrase[i];
+    }
     
     // mux between computed hash and previous iteration's hash to get phrase hash to use
     // if degrees of separation = 0 use computed hash, else use hash from previous step
@@ -53,12 +97,13 @@ template grapevine(num_felts) {
     phrase_mux.s <== is_degree_zero.out;
 
     // compute hash of given degree secret
-    // H(H(preimage), username, auth_secret[0])
+    // H(H(preimage), username, prev_relationship_secret)
     // where preimage is muxed depending on whether degree N is 1 or > 1
-    component degree_secret_hasher = Poseidon(3);
-    degree_secret_hasher.inputs[0] <== phrase_mux.out;
-    degree_secret_hasher.inputs[1] <== usernames[0];
-    degree_secret_hasher.inputs[2] <== auth_secrets[0];
+    component degree_secret_hasher = Poseidon(4);
+    degree_secret_hasher.inputs[0] <== DEGREE_SECRET_DOMAIN();
+    degree_secret_hasher.inputs[1] <== phrase_mux.out;
+    degree_secret_hasher.inputs[2] <== usernames[0];
+    degree_secret_hasher.inputs[3] <== prev_relationship_secret;
 
     // compare computed degree secret hash to prev degree secret hash
     component degree_secret_hash_match = IsEqual();
@@ -83,11 +128,42 @@ template grapevine(num_felts) {
     // constrain degree_secret_satisfied_mux to be true
     degree_secret_satisfied_mux.out === 1;
 
+    // derive the current user's relationship secret for this phrase
+    // H(auth_secret, H(preimage)), handed to the next degree instead of the auth secret
+    // it is per chain, not per recipient: the next username is unknown when this degree is proven
+    component relationship_secret = Poseidon(3);
+    relationship_secret.inputs[0] <== RELATIONSHIP_DOMAIN();
+    relationship_secret.inputs[1] <== auth_secret;
+    relationship_secret.inputs[2] <== phrase_mux.out;
+
     // compute the next username hash
-    component next_degree_secret_hash = Poseidon(3);
-    next_degree_secret_hash.inputs[0] <== phrase_mux.out;
-    next_degree_secret_hash.inputs[1] <== usernames[1];
-    next_degree_secret_hash.inputs[2] <== auth_secrets[1];
+    component next_degree_secret_hash = Poseidon(4);
+    next_degree_secret_hash.inputs[0] <== DEGREE_SECRET_DOMAIN();
+    next_degree_secret_hash.inputs[1] <== phrase_mux.out;
+    next_degree_secret_hash.inputs[2] <== usernames[1];
+    next_degree_secret_hash.inputs[3] <== relationship_secret.out;
+
+    // insert the current username at index degrees_of_separation of the path tree
+    // leaf H(username, salt), where the salt keeps undisclosed usernames hidden
+    component path_leaf = Poseidon(3);
+    path_leaf.inputs[0] <== PATH_LEAF_DOMAIN();
+    path_leaf.inputs[1] <== usernames[1];
+    path_leaf.inputs[2] <== path_salt;
+
+    // extend the path accumulator with the current user's salted leaf, so the public state only
+    // reveals the path to verifiers the users disclosed their salts to
+    // H(path accumulator, H(username, salt))
+    component path_accumulator = Poseidon(3);
+    path_accumulator.inputs[0] <== PATH_ACCUMULATOR_DOMAIN();
+    path_accumulator.inputs[1] <== given_path_accumulator;
+    path_accumulator.inputs[2] <== path_leaf.out;
+
+    component path_tree = PathTreeInsert(path_depth);
+    path_tree.index <== degrees_of_separation;
+    path_tree.leaf <== path_leaf.out;
+    path_tree.old_root <== given_path_root;
+    path_tree.siblings <== path_siblings;
+    path_tree.enabled <== is_logic_step;
 
     // mux step_out signal according to whether or not this is a chaff step
     component chaff_mux = ChaffMux();
@@ -95,11 +171,15 @@ template grapevine(num_felts) {
     chaff_mux.given_phrase_hash <== given_phrase_hash;
     chaff_mux.given_degree_secret_hash <== given_degree_secret_hash;
     chaff_mux.is_chaff_step <== is_chaff_step;
+    chaff_mux.given_path_accumulator <== given_path_accumulator;
+    chaff_mux.given_path_root <== given_path_root;
     chaff_mux.computed_phrase_hash <== phrase_mux.out;
     chaff_mux.computed_degree_secret_hash <== next_degree_secret_hash.out;
+    chaff_mux.computed_path_accumulator <== path_accumulator.out;
+    chaff_mux.computed_path_root <== path_tree.new_root;
 
     // wire output signals
     ivc_output <== chaff_mux.out;
 }
 
-component main { public [ivc_input] } = grapevine(6);
+component main { public [ivc_input] } = grapevine(6, 8);
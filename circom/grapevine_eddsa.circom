@@ -0,0 +1,210 @@
+pragma circom 2.1.6;
+
+include "node_modules/circomlib/circuits/poseidon.circom";
+include "node_modules/circomlib/circuits/mux1.circom";
+include "node_modules/circomlib/circuits/comparators.circom";
+include "node_modules/circomlib/circuits/gates.circom";
+include "node_modules/circomlib/circuits/eddsaposeidon.circom";
+include "./templates/chaff.circom";
+include "./templates/canonical.circom";
+include "./templates/domains.circom";
+include "./templates/path_tree.circom";
+
+// grapevine where each degree link is authorized by an EdDSA signature of the previous user
+template grapevine_eddsa(num_felts, path_depth) {
+
+    // in_out schema
+    // 0: degrees of separation
+    // 1: secret hash from previous step
+    // 2: hash of username + public key from previous step
+    // 3: chaff
+    // 4: running hash of the salted path leaves of the users on the path
+    // 5: root of the merkle tree of salted usernames on the path
+
+    signal input ivc_input[6];
+    signal output ivc_output[6];
+
+    // external inputs at each folding step
+    signal input external_inputs[num_felts+2+2+3+2+1+path_depth];
+    signal phrase[num_felts]; // secret phrase, if first iteration
+    for (var i=0; i<num_felts; i++) {
+        phrase[i] <== external_inputs[i];
+    }
+    signal usernames[2]; // prev username, current username
+    usernames[0]<==external_inputs[num_felts];
+    usernames[1]<==external_inputs[num_felts+1];
+    signal prev_pubkey[2]; // prev degree's babyjubjub public key (Ax, Ay)
+    prev_pubkey[0]<==external_inputs[num_felts+2];
+    prev_pubkey[1]<==external_inputs[num_felts+3];
+    signal link_signature[3]; // prev degree's signature over the link (R8x, R8y, S)
+    link_signature[0]<==external_inputs[num_felts+4];
+    link_signature[1]<==external_inputs[num_felts+5];
+    link_signature[2]<==external_inputs[num_felts+6];
+    signal pubkey[2]; // current degree's babyjubjub public key (Ax, Ay)
+    pubkey[0]<==external_inputs[num_felts+7];
+    pubkey[1]<==external_inputs[num_felts+8];
+    // salt of the current username's leaf in the path tree
+    signal path_salt <== external_inputs[num_felts+9];
+    // siblings of the current username's slot in the path tree
+    signal path_siblings[path_depth];
+    for (var i=0; i<path_depth; i++) {
+        path_siblings[i] <== external_inputs[num_felts+9+1+i];
+    }
+
+    // phrase chunks and usernames must be canonical 31 byte encodings, checked for chaff too
+    component canonical = CanonicalFelts(num_felts + 2);
+    for (var i=0; i<num_felts; i++) {
+        canonical.in[i] <== phrase[i];
+    }
+    canonical.in[num_felts] <== usernames[0];
+    canonical.in[num_felts+1] <== usernames[1];
+
+    // name inputs from step_in
+    signal degrees_of_separation <== ivc_input[0];
+    signal given_phrase_hash <== ivc_input[1];
+    signal given_degree_secret_hash <== ivc_input[2];
+    signal is_chaff_step <== ivc_input[3];
+    signal given_path_accumulator <== ivc_input[4];
+    signal given_path_root <== ivc_input[5];
+
+    // determine whether degrees of separation from secret is zero
+    component is_degree_zero = IsZero();
+    is_degree_zero.in <== degrees_of_separation;
+
+    // sonobe derives the r1cs by synthesizing a step over an all-zero state and external inputs,
+    // which must have a witness. that step is told apart by its zero path root, as chains start
+    // from the empty tree root (pinned by the verifier) and later roots are poseidon outputs
+    component is_shape_step = IsZero();
+    is_shape_step.in <== given_path_root;
+    signal is_logic_step <== (1 - is_chaff_step) * (1 - is_shape_step.out);
+
+    // the previous username is 0 exactly at degree 0, and logic steps need a current username
+    // (chaff steps are exempt as their usernames are random)
+    usernames[0] * is_degree_zero.out === 0;
+    signal is_logic_past_degree_zero <== (1 - is_degree_zero.out) * is_logic_step;
+    component is_prev_username_zero = IsZero();
+    is_prev_username_zero.in <== usernames[0];
+    is_prev_username_zero.out * is_logic_past_degree_zero === 0;
+    component is_username_zero = IsZero();
+    is_username_zero.in <== usernames[1];
+    is_username_zero.out * is_logic_step === 0;
+
+    // compute poseidon hash of secret
+    // same as the word essentially
+    component phrase_hasher = Poseidon(num_felts + 1);
+    phrase_hasher.inputs[0] <== PHRASE_DOMAIN();
+    for (var i=0; i<num_felts; i++) {
+        phrase_hasher.inputs[i+1] <== phrase[i];
+    }
+
+    // mux between computed hash and previous iteration's hash to get phrase hash to use
+    // if degrees of separation = 0 use computed hash, else use hash from previous step
+    component phrase_mux = Mux1();
+    phrase_mux.c[0] <== given_phrase_hash;
+    phrase_mux.c[1] <== phrase_hasher.out;
+    phrase_mux.s <== is_degree_zero.out;
+
+    // compute hash of given degree secret
+    // H(H(preimage), username, Ax, Ay)
+    // where preimage is muxed depending on whether degree N is 1 or > 1
+    component degree_secret_hasher = Poseidon(5);
+    degree_secret_hasher.inputs[0] <== PUBKEY_DEGREE_SECRET_DOMAIN();
+    degree_secret_hasher.inputs[1] <== phrase_mux.out;
+    degree_secret_hasher.inputs[2] <== usernames[0];
+    degree_secret_hasher.inputs[3] <== prev_pubkey[0];
+    degree_secret_hasher.inputs[4] <== prev_pubkey[1];
+
+    // compare computed degree secret hash to prev degree secret hash
+    component degree_secret_hash_match = IsEqual();
+    degree_secret_hash_match.in[0] <== degree_secret_hasher.out;
+    degree_secret_hash_match.in[1] <== given_degree_secret_hash;
+
+    // create boolean that is true if either is true:
+    //  - given degree secret hash matches computed hash
+    //  - is a chaff step
+    component degree_secret_match_or_chaff = OR();
+    degree_secret_match_or_chaff.a <== degree_secret_hash_match.out;
+    degree_secret_match_or_chaff.b <== is_chaff_step;
+
+    // create boolean that is muxes according to:
+    //  - if degrees of separation = 0, always true (no check needed)
+    //  - if degree of separation > 0, return output of degree_secret_match_or_chaff
+    component degree_secret_satisfied_mux = Mux1();
+    degree_secret_satisfied_mux.c[0] <== degree_secret_match_or_chaff.out;
+    degree_secret_satisfied_mux.c[1] <== 1;
+    degree_secret_satisfied_mux.s <== is_degree_zero.out;
+
+    // constrain degree_secret_satisfied_mux to be true
+    degree_secret_satisfied_mux.out === 1;
+
+    // message signed by the prev degree to authorize the link to the current user and key
+    // H(current username, Ax, Ay, H(preimage))
+    component link_message = Poseidon(5);
+    link_message.inputs[0] <== LINK_DOMAIN();
+    link_message.inputs[1] <== usernames[1];
+    link_message.inputs[2] <== pubkey[0];
+    link_message.inputs[3] <== pubkey[1];
+    link_message.inputs[4] <== phrase_mux.out;
+
+    // only check the signature if degrees of separation > 0 and this is not a chaff step
+    signal not_degree_zero <== 1 - is_degree_zero.out;
+    signal link_enabled <== not_degree_zero * (1 - is_chaff_step);
+
+    // verify the prev degree's signature over the link
+    component link_verifier = EdDSAPoseidonVerifier();
+    link_verifier.enabled <== link_enabled;
+    link_verifier.Ax <== prev_pubkey[0];
+    link_verifier.Ay <== prev_pubkey[1];
+    link_verifier.R8x <== link_signature[0];
+    link_verifier.R8y <== link_signature[1];
+    link_verifier.S <== link_signature[2];
+    link_verifier.M <== link_message.out;
+
+    // compute the next username hash
+    component next_degree_secret_hash = Poseidon(5);
+    next_degree_secret_hash.inputs[0] <== PUBKEY_DEGREE_SECRET_DOMAIN();
+    next_degree_secret_hash.inputs[1] <== phrase_mux.out;
+    next_degree_secret_hash.inputs[2] <== usernames[1];
+    next_degree_secret_hash.inputs[3] <== pubkey[0];
+    next_degree_secret_hash.inputs[4] <== pubkey[1];
+
+    // insert the current username at index degrees_of_separation of the path tree
+    // leaf H(username, salt), where the salt keeps undisclosed usernames hidden
+    component path_leaf = Poseidon(3);
+    path_leaf.inputs[0] <== PATH_LEAF_DOMAIN();
+    path_leaf.inputs[1] <== usernames[1];
+    path_leaf.inputs[2] <== path_salt;
+
+    // extend the path accumulator with the current user's salted leaf, so the public state only
+    // reveals the path to verifiers the users disclosed their salts to
+    // H(path accumulator, H(username, salt))
+    component path_accumulator = Poseidon(3);
+    path_accumulator.inputs[0] <== PATH_ACCUMULATOR_DOMAIN();
+    path_accumulator.inputs[1] <== given_path_accumulator;
+    path_accumulator.inputs[2] <== path_leaf.out;
+
+    component path_tree = PathTreeInsert(path_depth);
+    path_tree.index <== degrees_of_separation;
+    path_tree.leaf <== path_leaf.out;
+    path_tree.old_root <== given_path_root;
+    path_tree.siblings <== path_siblings;
+    path_tree.enabled <== is_logic_step;
+
+    // mux step_out signal according to whether or not this is a chaff step
+    component chaff_mux = ChaffMux();
+    chaff_mux.degrees_of_separation <== degrees_of_separation;
+    chaff_mux.given_phrase_hash <== given_phrase_hash;
+    chaff_mux.given_degree_secret_hash <== given_degree_secret_hash;
+    chaff_mux.is_chaff_step <== is_chaff_step;
+    chaff_mux.given_path_accumulator <== given_path_accumulator;
+    chaff_mux.given_path_root <== given_path_root;
+    chaff_mux.computed_phrase_hash <== phrase_mux.out;
+    chaff_mux.computed_degree_secret_hash <== next_degree_secret_hash.out;
+    chaff_mux.computed_path_accumulator <== path_accumulator.out;
+    chaff_mux.computed_path_root <== path_tree.new_root;
+
+    // wire output signals
+    ivc_output <== chaff_mux.out;
+}
+
+component main { public [ivc_input] } = grapevine_eddsa(6, 8);
@@ -0,0 +1,16 @@
+pragma circom 2.1.6;
+
+include "../node_modules/circomlib/circuits/bitify.circom";
+
+// Constrains each input to fit in 248 bits, the 31 bytes a phrase chunk or username is packed into
+// without it, values >= 2^248 (e.g. the field element of a username shifted by the modulus) would
+// be accepted as encodings of strings
+template CanonicalFelts(n) {
+    signal input in[n];
+
+    component bits[n];
+    for (var i=0; i<n; i++) {
+        bits[i] = Num2Bits(248);
+        bits[i].in <== in[i];
+    }
+}
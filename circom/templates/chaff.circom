@@ -10,17 +10,21 @@ template ChaffMux() {
     signal input given_phrase_hash;
     signal input given_degree_secret_hash;
     signal input is_chaff_step;
+    signal input given_path_accumulator;
+    signal input given_path_root;
     // computed inputs from circuit
     signal input computed_phrase_hash;
     signal input computed_degree_secret_hash;
+    signal input computed_path_accumulator;
+    signal input computed_path_root;
     // output formatted for step_out
-    signal output out[4];
+    signal output out[6];
 
     // constrain is_chaff_step to be 0 or 1
     is_chaff_step * (is_chaff_step - 1) === 0;
 
     // mux 3 different inputs selected by is_chaff_step
-    component mux = MultiMux1(3);
+    component mux = MultiMux1(5);
     mux.s <== is_chaff_step;
 
     // mux the degree of separation
@@ -38,6 +42,16 @@ template ChaffMux() {
     mux.c[2][0] <== computed_degree_secret_hash;
     mux.c[2][1] <== given_degree_secret_hash;
 
+    // mux the path accumulator
+    // if ! chaff step, grab the accumulator extended with the current username
+    mux.c[3][0] <== computed_path_accumulator;
+    mux.c[3][1] <== given_path_accumulator;
+
+    // mux the path tree root
+    // if ! chaff step, grab the root with the current username inserted
+    mux.c[4][0] <== computed_path_root;
+    mux.c[4][1] <== given_path_root;
+
     // flip chaff step
     component flipped_chaff_step = IsZero();
     flipped_chaff_step.in <== is_chaff_step;
@@ -47,4 +61,6 @@ template ChaffMux() {
     out[1] <== mux.out[1];
     out[2] <== mux.out[2];
     out[3] <== flipped_chaff_step.out;
+    out[4] <== mux.out[3];
+    out[5] <== mux.out[4];
 }
\ No newline at end of file
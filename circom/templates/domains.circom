@@ -0,0 +1,42 @@
+pragma circom 2.1.6;
+
+// domain tags prepended to the inputs of every poseidon hash specific to grapevine, separating
+// them from other uses of poseidon over the same inputs
+// each tag is the ascii string "grapevine/v<circuit version>/<name>" read as a big endian integer
+// and must match the tags in src/utils/hash.rs
+// merkle nodes are left untagged as their leaves are already tagged
+
+// "grapevine/v2/phrase"
+function PHRASE_DOMAIN() {
+    return 0x677261706576696e652f76322f706872617365;
+}
+
+// "grapevine/v2/degree_secret"
+function DEGREE_SECRET_DOMAIN() {
+    return 0x677261706576696e652f76322f6465677265655f736563726574;
+}
+
+// "grapevine/v2/pk_degree_secret"
+function PUBKEY_DEGREE_SECRET_DOMAIN() {
+    return 0x677261706576696e652f76322f706b5f6465677265655f736563726574;
+}
+
+// "grapevine/v2/relationship"
+function RELATIONSHIP_DOMAIN() {
+    return 0x677261706576696e652f76322f72656c6174696f6e73686970;
+}
+
+// "grapevine/v2/link"
+function LINK_DOMAIN() {
+    return 0x677261706576696e652f76322f6c696e6b;
+}
+
+// "grapevine/v2/path_accumulator"
+function PATH_ACCUMULATOR_DOMAIN() {
+    return 0x677261706576696e652f76322f706174685f616363756d756c61746f72;
+}
+
+// "grapevine/v2/path_leaf"
+function PATH_LEAF_DOMAIN() {
+    return 0x677261706576696e652f76322f706174685f6c656166;
+}
@@ -0,0 +1,60 @@
+pragma circom 2.1.6;
+
+include "../node_modules/circomlib/circuits/poseidon.circom";
+include "../node_modules/circomlib/circuits/switcher.circom";
+include "../node_modules/circomlib/circuits/bitify.circom";
+
+// computes the root of a binary poseidon merkle tree from a leaf and its path
+template PathTreeRoot(depth) {
+    signal input leaf;
+    signal input index_bits[depth]; // little endian, bit i selects the side at level i
+    signal input siblings[depth];
+    signal output root;
+
+    component switchers[depth];
+    component hashers[depth];
+    signal levels[depth + 1];
+    levels[0] <== leaf;
+    for (var i = 0; i < depth; i++) {
+        // if the bit is set the current node is the right child
+        switchers[i] = Switcher();
+        switchers[i].sel <== index_bits[i];
+        switchers[i].L <== levels[i];
+        switchers[i].R <== siblings[i];
+        hashers[i] = Poseidon(2);
+        hashers[i].inputs[0] <== switchers[i].outL;
+        hashers[i].inputs[1] <== switchers[i].outR;
+        levels[i + 1] <== hashers[i].out;
+    }
+    root <== levels[depth];
+}
+
+// inserts a leaf into an empty slot of the path tree
+// the tree holds 2^depth leaves, which caps chains at 2^depth degrees
+template PathTreeInsert(depth) {
+    signal input index;
+    signal input leaf;
+    signal input old_root;
+    signal input siblings[depth];
+    signal input enabled;
+    signal output new_root;
+
+    // decompose the index, which also bounds it by the capacity of the tree. disabled insertions
+    // use slot 0, so that chaff steps are not bound by the capacity
+    component index_bits = Num2Bits(depth);
+    index_bits.in <== index * enabled;
+
+    // the slot must be empty in the old tree
+    component old_tree = PathTreeRoot(depth);
+    old_tree.leaf <== 0;
+    old_tree.index_bits <== index_bits.out;
+    old_tree.siblings <== siblings;
+    (old_tree.root - old_root) * enabled === 0;
+
+    // the same path with the leaf set gives the new root
+    component new_tree = PathTreeRoot(depth);
+    new_tree.leaf <== leaf;
+    new_tree.index_bits <== index_bits.out;
+    new_tree.siblings <== siblings;
+    new_root <== new_tree.root;
+}
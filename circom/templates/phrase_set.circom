@@ -0,0 +1,23 @@
+pragma circom 2.1.6;
+
+include "../node_modules/circomlib/circuits/bitify.circom";
+include "./path_tree.circom";
+
+// checks that a leaf is committed at some index of a merkle tree with the given root
+template MerkleMembership(depth) {
+    signal input leaf;
+    signal input index;
+    signal input siblings[depth];
+    signal input root;
+    signal input enabled;
+
+    // decompose the index, which also bounds it by the capacity of the tree
+    component index_bits = Num2Bits(depth);
+    index_bits.in <== index;
+
+    component tree = PathTreeRoot(depth);
+    tree.leaf <== leaf;
+    tree.index_bits <== index_bits.out;
+    tree.siblings <== siblings;
+    (tree.root - root) * enabled === 0;
+}
@@ -0,0 +1,172 @@
+#ifndef GRAPEVINE_H
+#define GRAPEVINE_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/*
+ Status returned by every fallible function of the C interface
+ */
+typedef enum GrapevineErrorCode {
+  GRAPEVINE_ERROR_CODE_OK = 0,
+  GRAPEVINE_ERROR_CODE_NULL_POINTER = 1,
+  GRAPEVINE_ERROR_CODE_INVALID_ARGUMENT = 2,
+  GRAPEVINE_ERROR_CODE_INPUTS_EMPTY = 3,
+  GRAPEVINE_ERROR_CODE_SONOBE = 4,
+  GRAPEVINE_ERROR_CODE_SERIALIZATION = 5,
+  GRAPEVINE_ERROR_CODE_IO = 6,
+  GRAPEVINE_ERROR_CODE_PANIC = 7,
+  GRAPEVINE_ERROR_CODE_CANCELLED = 8,
+  GRAPEVINE_ERROR_CODE_ENVELOPE_MISMATCH = 9,
+  GRAPEVINE_ERROR_CODE_OVERLOADED = 10,
+  GRAPEVINE_ERROR_CODE_TIMEOUT = 11,
+} GrapevineErrorCode;
+
+/*
+ Opaque handle to the grapevine circuit and its prover/ verifier params
+ */
+typedef struct GrapevineParams GrapevineParams;
+
+/*
+ Opaque handle to a proving session holding the current handoff of a chain
+ */
+typedef struct GrapevineProver GrapevineProver;
+
+/*
+ Byte buffer allocated by rust, release with `grapevine_buffer_free`
+ */
+typedef struct GrapevineBuffer {
+  uint8_t *data;
+  uintptr_t len;
+} GrapevineBuffer;
+
+/*
+ Derives the params published under `GRAPEVINE_DOMAIN` for the circuit at the given paths
+
+ # Safety
+ `r1cs_path` and `wasm_path` must be valid nul-terminated strings and `out` a valid pointer
+ */
+enum GrapevineErrorCode grapevine_params_setup(const char *r1cs_path,
+                                               const char *wasm_path,
+                                               struct GrapevineParams **out);
+
+/*
+ Loads params serialized with `grapevine_params_serialize` for the circuit at the given paths
+
+ # Safety
+ `r1cs_path` and `wasm_path` must be valid nul-terminated strings, `data` must point to `len`
+ readable bytes and `out` must be a valid pointer
+ */
+enum GrapevineErrorCode grapevine_params_load(const char *r1cs_path,
+                                              const char *wasm_path,
+                                              const uint8_t *data,
+                                              uintptr_t len,
+                                              struct GrapevineParams **out);
+
+/*
+ Serializes params into a buffer
+
+ # Safety
+ `params` must be a handle returned by this library and `out` a valid pointer
+ */
+enum GrapevineErrorCode grapevine_params_serialize(const struct GrapevineParams *params,
+                                                   struct GrapevineBuffer *out);
+
+/*
+ Releases params, prover sessions created from them remain valid
+
+ # Safety
+ `params` must be null or a handle returned by this library that was not freed yet
+ */
+void grapevine_params_free(struct GrapevineParams *params);
+
+/*
+ Starts a proving session, either for a new chain (`handoff` null) or from a received handoff
+
+ # Safety
+ `params` must be a handle returned by this library, `handoff` must be null or point to
+ `handoff_len` readable bytes and `out` must be a valid pointer
+ */
+enum GrapevineErrorCode grapevine_prover_new(const struct GrapevineParams *params,
+                                             const uint8_t *handoff,
+                                             uintptr_t handoff_len,
+                                             struct GrapevineProver **out);
+
+/*
+ Proves degree 1 from knowledge of the phrase
+
+ # Safety
+ `prover` must be a handle returned by this library and all strings valid nul-terminated
+ strings, `auth_secret` holding a decimal integer
+ */
+enum GrapevineErrorCode grapevine_prover_prove_first(struct GrapevineProver *prover,
+                                                     const char *phrase,
+                                                     const char *username,
+                                                     const char *auth_secret);
+
+/*
+ Derives the relationship secret to hand to the next user together with the session's handoff
+ and writes it into a buffer as a decimal string
+
+ # Safety
+ `prover` must be a handle returned by this library, `auth_secret` a valid nul-terminated
+ string holding a decimal integer and `out` a valid pointer
+ */
+enum GrapevineErrorCode grapevine_prover_relationship_secret(const struct GrapevineProver *prover,
+                                                             const char *auth_secret,
+                                                             struct GrapevineBuffer *out);
+
+/*
+ Extends the chain held by the session by one degree of separation
+
+ # Safety
+ `prover` must be a handle returned by this library and all strings valid nul-terminated
+ strings, secrets holding decimal integers
+ */
+enum GrapevineErrorCode grapevine_prover_extend(struct GrapevineProver *prover,
+                                                const char *prev_username,
+                                                const char *prev_relationship_secret,
+                                                const char *username,
+                                                const char *auth_secret);
+
+/*
+ Serializes the current handoff of the session into a buffer, wrapped in a proof envelope
+
+ # Safety
+ `prover` must be a handle returned by this library and `out` a valid pointer
+ */
+enum GrapevineErrorCode grapevine_prover_handoff(const struct GrapevineProver *prover,
+                                                 struct GrapevineBuffer *out);
+
+/*
+ Releases a proving session
+
+ # Safety
+ `prover` must be null or a handle returned by this library that was not freed yet
+ */
+void grapevine_prover_free(struct GrapevineProver *prover);
+
+/*
+ Verifies a serialized handoff, rejecting envelopes proven with another circuit or params
+
+ # Safety
+ `params` must be a handle returned by this library and `data` must point to `len` readable
+ bytes
+ */
+enum GrapevineErrorCode grapevine_verify(const struct GrapevineParams *params,
+                                         const uint8_t *data,
+                                         uintptr_t len);
+
+/*
+ Releases a buffer returned by this library
+
+ # Safety
+ `buffer` must have been returned by this library and not freed yet
+ */
+void grapevine_buffer_free(struct GrapevineBuffer buffer);
+
+#endif /* GRAPEVINE_H */
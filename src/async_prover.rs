@@ -0,0 +1,329 @@
+use ark_bn254::Fr;
+use num_bigint::BigInt;
+use sonobe::frontend::FCircuit;
+use std::{future::Future, sync::Arc, time::Duration};
+use tokio::{sync::Semaphore, task, time};
+
+use crate::batch::ProverContext;
+use crate::errors::GrapevineError;
+use crate::handoff::Handoff;
+use crate::nova::{extend_with_control, prove_first_with_control, verify};
+use crate::params::{transparent_nova_setup, GrapevineVerifierParams, GRAPEVINE_DOMAIN};
+use crate::progress::{CancellationToken, ProveControl};
+
+/** Limits of an async prover */
+#[derive(Clone, Copy, Debug)]
+pub struct AsyncProverConfig {
+    // jobs running on the blocking pool at once
+    pub max_concurrent_jobs: usize,
+    // jobs waiting for a free slot, further jobs are rejected with `Overloaded`
+    pub max_queued_jobs: usize,
+    // time a job may take from submission to completion, including waiting for a slot
+    pub job_timeout: Duration,
+}
+
+impl Default for AsyncProverConfig {
+    fn default() -> Self {
+        let parallelism = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Self {
+            max_concurrent_jobs: parallelism,
+            max_queued_jobs: 4 * parallelism,
+            job_timeout: Duration::from_secs(300),
+        }
+    }
+}
+
+/**
+ * Async facade over setup, proving and verification for tokio services
+ * @dev the blocking work runs on tokio's blocking pool, gated by a semaphore so that at most
+ *      `max_concurrent_jobs` run at once. a job that times out is cancelled through its
+ *      `CancellationToken` and keeps its slot until it actually stops
+ */
+pub struct AsyncProver<FC: FCircuit<Fr>> {
+    context: Arc<ProverContext<FC>>,
+    verifier_params: Arc<GrapevineVerifierParams>,
+    config: AsyncProverConfig,
+    // admission of submitted jobs, running or queued
+    admitted: Arc<Semaphore>,
+    // slots on the blocking pool
+    running: Arc<Semaphore>,
+}
+
+impl<FC> AsyncProver<FC>
+where
+    FC: FCircuit<Fr> + Send + Sync + 'static,
+{
+    /**
+     * Wraps existing params
+     *
+     * @param context - the prover params and circuit to prove with
+     * @param verifier_params - the params to verify with
+     * @param config - the limits of the prover
+     * @return - the async prover
+     */
+    pub fn new(
+        context: ProverContext<FC>,
+        verifier_params: GrapevineVerifierParams,
+        config: AsyncProverConfig,
+    ) -> Self {
+        Self {
+            context: Arc::new(context),
+            verifier_params: Arc::new(verifier_params),
+            config,
+            admitted: Arc::new(Semaphore::new(
+                config.max_concurrent_jobs + config.max_queued_jobs,
+            )),
+            running: Arc::new(Semaphore::new(config.max_concurrent_jobs)),
+        }
+    }
+
+    /**
+     * Derives the params published under GRAPEVINE_DOMAIN for a circuit on the blocking pool
+     *
+     * @param f_circuit - the circuit to generate params for
+     * @param config - the limits of the prover
+     * @return - the async prover
+     */
+    pub async fn setup(f_circuit: FC, config: AsyncProverConfig) -> Result<Self, GrapevineError> {
+        let (context, verifier_params) = join(task::spawn_blocking(move || {
+            let (prover_params, verifier_params) =
+                transparent_nova_setup(f_circuit.clone(), GRAPEVINE_DOMAIN)?;
+            let context = ProverContext {
+                prover_params,
+                f_circuit,
+            };
+            Ok((context, verifier_params))
+        }))
+        .await?;
+        Ok(Self::new(context, verifier_params, config))
+    }
+
+    /** Prover params and circuit the prover was set up with */
+    pub fn context(&self) -> &ProverContext<FC> {
+        &self.context
+    }
+
+    /**
+     * Proves the first degree of separation, see `nova::prove_first`
+     *
+     * @param phrase - the secret phrase
+     * @param username - the username of the prover
+     * @param auth_secret - the auth secret of the prover
+     * @return - the handoff containing the degree 1 proof
+     */
+    pub async fn prove_first(
+        &self,
+        phrase: String,
+        username: String,
+        auth_secret: BigInt,
+    ) -> Result<Handoff, GrapevineError> {
+        self.run(move |context, _, control| {
+            prove_first_with_control(
+                &context.prover_params,
+                context.f_circuit.clone(),
+                phrase,
+                username,
+                auth_secret,
+                control,
+            )
+        })
+        .await
+    }
+
+    /**
+     * Extends a chain by one degree of separation, see `nova::extend`
+     *
+     * @param handoff - the state of the chain handed off by the previous user
+     * @param prev_username - the username of the previous user
+     * @param prev_relationship_secret - the relationship secret derived by the previous user
+     * @param username - the username of the prover
+     * @param auth_secret - the auth secret of the prover
+     * @return - the handoff containing the extended proof
+     */
+    pub async fn extend(
+        &self,
+        handoff: Handoff,
+        prev_username: String,
+        prev_relationship_secret: BigInt,
+        username: String,
+        auth_secret: BigInt,
+    ) -> Result<Handoff, GrapevineError> {
+        self.run(move |context, _, control| {
+            extend_with_control(
+                &context.prover_params,
+                context.f_circuit.clone(),
+                handoff,
+                prev_username,
+                prev_relationship_secret,
+                username,
+                auth_secret,
+                control,
+            )
+        })
+        .await
+    }
+
+    /**
+     * Verifies the ivc proof contained in a handoff
+     * @dev verification can not be cancelled, so a timed out verification keeps its slot until
+     *      it completes
+     *
+     * @param handoff - the handoff to verify
+     */
+    pub async fn verify(&self, handoff: Handoff) -> Result<(), GrapevineError> {
+        self.run(move |_, verifier_params, _| verify::<FC>(verifier_params, &handoff))
+            .await
+    }
+
+    /**
+     * Runs a job on the blocking pool within the limits of the prover
+     *
+     * @param job - the blocking work, given the params and the control to honor cancellation
+     * @return - the result of the job, `Overloaded` if the queue is full or `Timeout` if the job
+     *           did not complete in time
+     */
+    async fn run<T, F>(&self, job: F) -> Result<T, GrapevineError>
+    where
+        T: Send + 'static,
+        F: FnOnce(
+                &ProverContext<FC>,
+                &GrapevineVerifierParams,
+                &ProveControl,
+            ) -> Result<T, GrapevineError>
+            + Send
+            + 'static,
+    {
+        let _admitted = self
+            .admitted
+            .clone()
+            .try_acquire_owned()
+            .map_err(|_| GrapevineError::Overloaded)?;
+        let token = CancellationToken::new();
+        let control = ProveControl {
+            observer: None,
+            cancel: Some(token.clone()),
+        };
+        let (context, verifier_params) = (self.context.clone(), self.verifier_params.clone());
+        let running = self.running.clone();
+        let job = async move {
+            let slot = running
+                .acquire_owned()
+                .await
+                .map_err(|_| GrapevineError::Cancelled)?;
+            join(task::spawn_blocking(move || {
+                // held until the job stops, even if the caller gave up on it
+                let _slot = slot;
+                job(&context, &verifier_params, &control)
+            }))
+            .await
+        };
+        match time::timeout(self.config.job_timeout, job).await {
+            Ok(result) => result,
+            Err(_) => {
+                token.cancel();
+                Err(GrapevineError::Timeout)
+            }
+        }
+    }
+}
+
+/** Awaits a blocking task, resuming its panic on the caller */
+async fn join<T>(
+    handle: impl Future<Output = Result<Result<T, GrapevineError>, task::JoinError>>,
+) -> Result<T, GrapevineError> {
+    match handle.await {
+        Ok(result) => result,
+        Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+        Err(_) => Err(GrapevineError::Cancelled),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::circom::grapevine_circuit;
+    use crate::utils::inputs::{derive_relationship_secret, random_f_bigint};
+    use sonobe::frontend::circom::CircomFCircuit;
+    use std::path::PathBuf;
+
+    async fn prover(config: AsyncProverConfig) -> AsyncProver<CircomFCircuit<Fr>> {
+        let f_circuit = grapevine_circuit(
+            PathBuf::from("./circom/artifacts/grapevine.r1cs"),
+            PathBuf::from("./circom/artifacts/grapevine.wasm"),
+        )
+        .unwrap();
+        AsyncProver::setup(f_circuit, config).await.unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_async_chain() {
+        let prover = prover(AsyncProverConfig::default()).await;
+        let auth_secrets = [random_f_bigint::<Fr>(), random_f_bigint::<Fr>()];
+        let handoff = prover
+            .prove_first(
+                String::from("This is a secret"),
+                String::from("alice"),
+                auth_secrets[0].clone(),
+            )
+            .await
+            .unwrap();
+        prover.verify(handoff.clone()).await.unwrap();
+
+        let relationship_secret = derive_relationship_secret(&auth_secrets[0], handoff.z_i[1]);
+        let handoff = prover
+            .extend(
+                handoff,
+                String::from("alice"),
+                relationship_secret,
+                String::from("bob"),
+                auth_secrets[1].clone(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(handoff.z_i[0], Fr::from(2));
+        prover.verify(handoff).await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_async_limits() {
+        // a single slot and no queue: a second concurrent job is rejected
+        let prover = prover(AsyncProverConfig {
+            max_concurrent_jobs: 1,
+            max_queued_jobs: 0,
+            job_timeout: Duration::from_secs(300),
+        })
+        .await;
+        let prove = |username: &str| {
+            prover.prove_first(
+                String::from("This is a secret"),
+                String::from(username),
+                random_f_bigint::<Fr>(),
+            )
+        };
+        let (first, second) = tokio::join!(prove("alice"), prove("bob"));
+        assert!(first.is_ok());
+        assert!(matches!(second, Err(GrapevineError::Overloaded)));
+
+        // a job that does not complete in time is cancelled
+        let prover = AsyncProver::new(
+            ProverContext {
+                prover_params: prover.context().prover_params.clone(),
+                f_circuit: prover.context().f_circuit.clone(),
+            },
+            (*prover.verifier_params).clone(),
+            AsyncProverConfig {
+                max_concurrent_jobs: 1,
+                max_queued_jobs: 0,
+                job_timeout: Duration::from_millis(1),
+            },
+        );
+        let result = prover
+            .prove_first(
+                String::from("This is a secret"),
+                String::from("alice"),
+                random_f_bigint::<Fr>(),
+            )
+            .await;
+        assert!(matches!(result, Err(GrapevineError::Timeout)));
+    }
+}
@@ -0,0 +1,100 @@
+use ark_bn254::{Fr, G1Projective as Projective};
+use ark_grumpkin::Projective as Projective2;
+use sonobe::{frontend::FCircuit, FoldingScheme};
+
+use crate::errors::GrapevineError;
+use crate::nova::prove_degree;
+use crate::utils::inputs::StepInputs;
+
+/**
+ * Folds a sequence of degrees with any folding scheme
+ *
+ * @param prover_params - the params of the folding scheme
+ * @param f_circuit - the grapevine circuit
+ * @param z_0 - the initial ivc state
+ * @param degrees - the inputs of the logic step of each degree
+ * @return - the folding scheme after proving every degree
+ */
+pub fn fold_degrees<FC, FS, I>(
+    prover_params: &FS::ProverParam,
+    f_circuit: FC,
+    z_0: Vec<Fr>,
+    degrees: &[I],
+) -> Result<FS, GrapevineError>
+where
+    FC: FCircuit<Fr>,
+    FS: FoldingScheme<Projective, Projective2, FC>,
+    I: StepInputs,
+{
+    let mut folding = FS::init(prover_params, f_circuit, z_0)?;
+    for inputs in degrees {
+        prove_degree(&mut folding, inputs)?;
+    }
+    Ok(folding)
+}
+
+/**
+ * Verifies the ivc proof of any folding scheme
+ *
+ * @param verifier_params - the params of the folding scheme
+ * @param folding - the folding scheme to verify the current state of
+ * @param z_0 - the initial ivc state
+ * @param num_steps - the number of steps folded, two per degree
+ */
+pub fn verify_folding<FC, FS>(
+    verifier_params: FS::VerifierParam,
+    folding: &FS,
+    z_0: Vec<Fr>,
+    num_steps: usize,
+) -> Result<(), GrapevineError>
+where
+    FC: FCircuit<Fr>,
+    FS: FoldingScheme<Projective, Projective2, FC>,
+{
+    let (running, incoming, cyclefold) = folding.instances();
+    FS::verify(
+        verifier_params,
+        z_0,
+        folding.state(),
+        Fr::from(num_steps as u64),
+        running,
+        incoming,
+        cyclefold,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::circom::grapevine_circuit;
+    use crate::nova::NovaType;
+    use crate::params::test_nova_setup;
+    use crate::utils::fixtures::alice_degree_1;
+    use crate::utils::inputs::get_z0;
+    use sonobe::frontend::circom::CircomFCircuit;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_fold_degrees_nova() {
+        let f_circuit = grapevine_circuit(
+            PathBuf::from("./circom/artifacts/grapevine.r1cs"),
+            PathBuf::from("./circom/artifacts/grapevine.wasm"),
+        )
+        .unwrap();
+        let (prover_params, verifier_params) = test_nova_setup(f_circuit.clone());
+        let (inputs, _) = alice_degree_1();
+        let z_0 = get_z0().to_vec();
+        let folding = fold_degrees::<_, NovaType<CircomFCircuit<Fr>>, _>(
+            &prover_params,
+            f_circuit,
+            z_0.clone(),
+            &[inputs],
+        )
+        .unwrap();
+        assert_eq!(folding.state()[0], Fr::from(1));
+        verify_folding(verifier_params.clone(), &folding, z_0.clone(), 2).unwrap();
+        // the proof does not verify for another number of steps
+        assert!(verify_folding(verifier_params, &folding, z_0, 4).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+use ark_bn254::Fr;
+use rayon::prelude::*;
+use sonobe::{frontend::FCircuit, FoldingScheme};
+use std::sync::Arc;
+
+use crate::errors::GrapevineError;
+use crate::handoff::Handoff;
+use crate::nova::{prove_degree, NovaType};
+use crate::params::GrapevineProverParams;
+use crate::utils::inputs::{get_z0, hash_phrase, CircomPrivateInput};
+use crate::utils::path::PathTree;
+
+/** Prover params and circuit shared by every job proven against them */
+pub struct ProverContext<FC: FCircuit<Fr>> {
+    pub prover_params: GrapevineProverParams,
+    pub f_circuit: FC,
+}
+
+/** A single degree to prove as part of a batch */
+pub struct BatchJob<FC: FCircuit<Fr>> {
+    pub context: Arc<ProverContext<FC>>,
+    // the chain to extend, or None to prove degree 1 from a phrase
+    pub handoff: Option<Handoff>,
+    // inputs of the logic step, the path insertion is filled in when the job is proven
+    pub inputs: CircomPrivateInput,
+}
+
+/** A folding scheme kept by a worker for one context, with its initial state */
+struct WorkerNova<FC: FCircuit<Fr>> {
+    context: Arc<ProverContext<FC>>,
+    nova: NovaType<FC>,
+    initial: Handoff,
+}
+
+/**
+ * Proves many independent degrees in parallel
+ * @dev each worker initializes one folding scheme per context and reuses it across jobs, so the
+ *      augmented r1cs and pedersen params are not rebuilt or copied for every job
+ *
+ * @param jobs - the degrees to prove
+ * @return - the resulting handoff of each job, in the order of the jobs
+ */
+pub fn prove_batch<FC>(jobs: Vec<BatchJob<FC>>) -> Vec<Result<Handoff, GrapevineError>>
+where
+    FC: FCircuit<Fr> + Send + Sync,
+{
+    jobs.into_par_iter()
+        .map_init(Vec::<WorkerNova<FC>>::new, |workers, job| {
+            prove_job(workers, job)
+        })
+        .collect()
+}
+
+fn prove_job<FC: FCircuit<Fr>>(
+    workers: &mut Vec<WorkerNova<FC>>,
+    job: BatchJob<FC>,
+) -> Result<Handoff, GrapevineError> {
+    let position = workers
+        .iter()
+        .position(|worker| Arc::ptr_eq(&worker.context, &job.context));
+    let worker = match position {
+        Some(position) => &mut workers[position],
+        None => {
+            let nova = NovaType::<FC>::init(
+                &job.context.prover_params,
+                job.context.f_circuit.clone(),
+                get_z0().to_vec(),
+            )?;
+            let initial = Handoff::from_nova(&nova, PathTree::new());
+            workers.push(WorkerNova {
+                context: job.context.clone(),
+                nova,
+                initial,
+            });
+            workers.last_mut().unwrap()
+        }
+    };
+    let handoff = job.handoff.unwrap_or_else(|| worker.initial.clone());
+    let phrase_hash = match &job.inputs.phrase {
+        Some(phrase) => hash_phrase(phrase)?,
+        None => handoff.z_i[1],
+    };
+    let mut path = handoff.path.clone();
+    let mut inputs = job.inputs;
+    if let (Some(username), Some(auth_secret)) = (&inputs.usernames[1], &inputs.auth_secret) {
+        inputs.path = Some(path.insert_user(username, auth_secret, phrase_hash)?);
+    }
+    handoff.load_into(&mut worker.nova);
+    prove_degree(&mut worker.nova, &inputs)?;
+    Ok(Handoff::from_nova(&worker.nova, path))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::circom::grapevine_circuit;
+    use crate::nova::verify;
+    use crate::params::test_nova_setup;
+    use crate::utils::inputs::random_f_bigint;
+    use sonobe::frontend::circom::CircomFCircuit;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_prove_batch() {
+        let f_circuit = grapevine_circuit(
+            PathBuf::from("./circom/artifacts/grapevine.r1cs"),
+            PathBuf::from("./circom/artifacts/grapevine.wasm"),
+        )
+        .unwrap();
+        let (prover_params, verifier_params) = test_nova_setup(f_circuit.clone());
+        let context = Arc::new(ProverContext {
+            prover_params,
+            f_circuit,
+        });
+
+        // degree 1 for two independent phrases
+        let jobs = ["alice", "bob"]
+            .iter()
+            .enumerate()
+            .map(|(i, username)| BatchJob {
+                context: context.clone(),
+                handoff: None,
+                inputs: CircomPrivateInput {
+                    phrase: Some(format!("phrase {}", i)),
+                    usernames: [None, Some(String::from(*username))],
+                    relationship_secret: None,
+                    auth_secret: Some(random_f_bigint::<Fr>()),
+                    path: None,
+                    chaff: false,
+                },
+            })
+            .collect::<Vec<BatchJob<CircomFCircuit<Fr>>>>();
+        let handoffs = prove_batch(jobs);
+
+        assert_eq!(handoffs.len(), 2);
+        for handoff in handoffs {
+            let handoff = handoff.unwrap();
+            assert_eq!(handoff.z_i[0], Fr::from(1));
+            assert_eq!(handoff.z_i[5], handoff.path.root());
+            verify::<CircomFCircuit<Fr>>(&verifier_params, &handoff).unwrap();
+        }
+    }
+}
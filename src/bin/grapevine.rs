@@ -0,0 +1,337 @@
+use ark_bn254::Fr;
+use clap::{Args, Parser, Subcommand};
+use color_eyre::eyre::{eyre, Result};
+use grapevine_sonobe::{
+    circom::{grapevine_circuit, grapevine_native_circuit},
+    envelope::{hex, Fingerprint, ProofEnvelope},
+    nova::{extend, prove_first, verify},
+    params::{
+        check_transparent_params, deserialize_params, serialize_params, transparent_nova_setup,
+        GRAPEVINE_DOMAIN,
+    },
+    utils::{
+        inputs::{decode_z_i, derive_relationship_secret},
+        path::{audit_path, derive_path_salt},
+    },
+};
+use num_bigint::BigInt;
+use sonobe::frontend::FCircuit;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+#[derive(Parser)]
+#[command(
+    name = "grapevine",
+    about = "Prove and verify grapevine degrees of separation"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Args, Clone)]
+struct CircuitArgs {
+    /// Path to the compiled grapevine r1cs
+    #[arg(long, default_value = "./circom/artifacts/grapevine.r1cs")]
+    r1cs: PathBuf,
+    /// Path to the compiled grapevine wasm
+    #[arg(long, default_value = "./circom/artifacts/grapevine.wasm")]
+    wasm: PathBuf,
+    /// Compute witnesses natively from the r1cs instead of running the wasm
+    #[arg(long)]
+    native_witness: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate prover params for the grapevine circuit
+    Setup {
+        #[command(flatten)]
+        circuit: CircuitArgs,
+        /// Where to write the params
+        #[arg(long)]
+        out: PathBuf,
+        /// Public domain string the pedersen generators are derived from
+        #[arg(long, default_value = GRAPEVINE_DOMAIN)]
+        domain: String,
+    },
+    /// Check that params were derived from a public domain string
+    CheckParams {
+        #[command(flatten)]
+        circuit: CircuitArgs,
+        #[arg(long)]
+        params: PathBuf,
+        #[arg(long, default_value = GRAPEVINE_DOMAIN)]
+        domain: String,
+    },
+    /// Prove degree 1 from knowledge of the phrase
+    ProveFirst {
+        #[command(flatten)]
+        circuit: CircuitArgs,
+        #[arg(long)]
+        params: PathBuf,
+        #[arg(long)]
+        phrase: String,
+        #[arg(long)]
+        username: String,
+        /// Auth secret of the prover as a decimal integer
+        #[arg(long)]
+        auth_secret: String,
+        /// Where to write the handoff
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Consume a handoff and prove one more degree of separation
+    Extend {
+        #[command(flatten)]
+        circuit: CircuitArgs,
+        #[arg(long)]
+        params: PathBuf,
+        /// Handoff received from the previous user
+        #[arg(long)]
+        handoff: PathBuf,
+        #[arg(long)]
+        prev_username: String,
+        /// Relationship secret derived by the previous user as a decimal integer
+        #[arg(long)]
+        prev_relationship_secret: String,
+        #[arg(long)]
+        username: String,
+        /// Auth secret of the prover as a decimal integer
+        #[arg(long)]
+        auth_secret: String,
+        /// Where to write the extended handoff
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Verify the proof contained in a handoff
+    Verify {
+        #[command(flatten)]
+        circuit: CircuitArgs,
+        #[arg(long)]
+        params: PathBuf,
+        #[arg(long)]
+        handoff: PathBuf,
+    },
+    /// Decode the ivc state (z_i) of a handoff
+    Inspect {
+        #[arg(long)]
+        handoff: PathBuf,
+        /// Comma separated `username:salt` pairs on the path to audit against the path
+        /// accumulator, with the salts each user gets from `path-salt`
+        #[arg(long, value_delimiter = ',')]
+        path: Option<Vec<String>>,
+    },
+    /// Derive the salt of a user's path leaf, disclosed to let a verifier audit the path
+    PathSalt {
+        /// Handoff of a chain the user is on
+        #[arg(long)]
+        handoff: PathBuf,
+        #[arg(long)]
+        username: String,
+        /// Auth secret of the user as a decimal integer
+        #[arg(long)]
+        auth_secret: String,
+    },
+    /// Derive the relationship secret to send to the next user along with a handoff
+    RelationshipSecret {
+        /// Handoff that will be sent to the next user
+        #[arg(long)]
+        handoff: PathBuf,
+        /// Auth secret of the sender as a decimal integer
+        #[arg(long)]
+        auth_secret: String,
+    },
+}
+
+fn load_envelope(path: &PathBuf) -> Result<ProofEnvelope> {
+    Ok(ProofEnvelope::from_bytes(&fs::read(path)?)?)
+}
+
+/** Reads the params file and fingerprints it together with the circuit */
+fn load_params(params: &Path, r1cs: &Path) -> Result<(Vec<u8>, Fingerprint)> {
+    let bytes = fs::read(params)?;
+    let fingerprint = Fingerprint::new(&fs::read(r1cs)?, &bytes);
+    Ok((bytes, fingerprint))
+}
+
+fn parse_secret(secret: &str) -> Result<BigInt> {
+    BigInt::from_str(secret).map_err(|_| eyre!("Secrets must be decimal integers"))
+}
+
+fn inspect(handoff: &PathBuf, path: &Option<Vec<String>>) -> Result<()> {
+    let envelope = load_envelope(handoff)?;
+    let handoff = &envelope.handoff;
+    let output = decode_z_i(&handoff.z_i).ok_or_else(|| eyre!("Handoff contains malformed z_i"))?;
+    println!("format version: {}", envelope.version);
+    println!(
+        "circuit digest: {}",
+        hex(&envelope.fingerprint.circuit_digest)
+    );
+    println!(
+        "params digest: {}",
+        hex(&envelope.fingerprint.params_digest)
+    );
+    println!("circuit version: {}", envelope.circuit_version);
+    println!("phrase width: {}", envelope.phrase_width);
+    println!("steps: {}", handoff.i);
+    println!("degree: {}", output.degree);
+    println!("phrase hash: {}", output.phrase_hash);
+    println!("degree secret hash: {}", output.degree_secret_hash);
+    println!("next step chaff: {}", output.next_step_chaff);
+    println!("path accumulator: {}", output.path_accumulator);
+    println!("path root: {}", output.path_root);
+    if let Some(path) = path {
+        let path = path
+            .iter()
+            .map(|user| {
+                let (username, salt) = user
+                    .split_once(':')
+                    .ok_or_else(|| eyre!("Path entries must be username:salt"))?;
+                let salt =
+                    Fr::from_str(salt).map_err(|_| eyre!("Salts must be decimal integers"))?;
+                Ok((String::from(username), salt))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        audit_path(&handoff.z_i, &path)?;
+        println!("Path verified");
+    }
+    Ok(())
+}
+
+fn relationship_secret(handoff: &PathBuf, auth_secret: &str) -> Result<()> {
+    let handoff = load_envelope(handoff)?.handoff;
+    let output = decode_z_i(&handoff.z_i).ok_or_else(|| eyre!("Handoff contains malformed z_i"))?;
+    let secret = derive_relationship_secret(&parse_secret(auth_secret)?, output.phrase_hash);
+    println!("{}", secret);
+    Ok(())
+}
+
+fn path_salt(handoff: &PathBuf, username: &String, auth_secret: &str) -> Result<()> {
+    let handoff = load_envelope(handoff)?.handoff;
+    let output = decode_z_i(&handoff.z_i).ok_or_else(|| eyre!("Handoff contains malformed z_i"))?;
+    let salt = derive_path_salt(&parse_secret(auth_secret)?, output.phrase_hash, username)?;
+    println!("{}", salt);
+    Ok(())
+}
+
+fn run<FC: FCircuit<Fr>>(command: Command, f_circuit: FC, r1cs: PathBuf) -> Result<()> {
+    match command {
+        Command::Setup { out, domain, .. } => {
+            let (prover_params, _) = transparent_nova_setup(f_circuit, &domain)?;
+            fs::write(&out, serialize_params(&prover_params)?)?;
+            println!("Wrote params to {}", out.display());
+        }
+        Command::CheckParams { params, domain, .. } => {
+            check_transparent_params(&fs::read(params)?, f_circuit, &domain)?;
+            println!("Params are derived from \"{}\"", domain);
+        }
+        Command::ProveFirst {
+            params,
+            phrase,
+            username,
+            auth_secret,
+            out,
+            ..
+        } => {
+            let (bytes, fingerprint) = load_params(&params, &r1cs)?;
+            let (prover_params, _) = deserialize_params(&bytes, f_circuit.clone())?;
+            let handoff = prove_first(
+                &prover_params,
+                f_circuit,
+                phrase,
+                username,
+                parse_secret(&auth_secret)?,
+            )?;
+            fs::write(&out, ProofEnvelope::seal(handoff, fingerprint).to_bytes()?)?;
+            println!("Wrote degree 1 handoff to {}", out.display());
+        }
+        Command::Extend {
+            params,
+            handoff,
+            prev_username,
+            prev_relationship_secret,
+            username,
+            auth_secret,
+            out,
+            ..
+        } => {
+            let (bytes, fingerprint) = load_params(&params, &r1cs)?;
+            let (prover_params, _) = deserialize_params(&bytes, f_circuit.clone())?;
+            let handoff = extend(
+                &prover_params,
+                f_circuit,
+                load_envelope(&handoff)?.open(&fingerprint)?,
+                prev_username,
+                parse_secret(&prev_relationship_secret)?,
+                username,
+                parse_secret(&auth_secret)?,
+            )?;
+            fs::write(&out, ProofEnvelope::seal(handoff, fingerprint).to_bytes()?)?;
+            println!("Wrote extended handoff to {}", out.display());
+        }
+        Command::Verify {
+            params, handoff, ..
+        } => {
+            let (bytes, fingerprint) = load_params(&params, &r1cs)?;
+            let handoff = load_envelope(&handoff)?.open(&fingerprint)?;
+            let (_, verifier_params) = deserialize_params(&bytes, f_circuit)?;
+            verify::<FC>(&verifier_params, &handoff)?;
+            println!("Proof verified");
+        }
+        Command::Inspect { handoff, path } => inspect(&handoff, &path)?,
+        Command::RelationshipSecret {
+            handoff,
+            auth_secret,
+        } => relationship_secret(&handoff, &auth_secret)?,
+        Command::PathSalt {
+            handoff,
+            username,
+            auth_secret,
+        } => path_salt(&handoff, &username, &auth_secret)?,
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    color_eyre::install()?;
+    #[cfg(feature = "tracing")]
+    tracing_subscriber::fmt()
+        .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
+        .with_writer(std::io::stderr)
+        .init();
+    let command = Cli::parse().command;
+    let args = match &command {
+        Command::Setup { circuit, .. }
+        | Command::CheckParams { circuit, .. }
+        | Command::ProveFirst { circuit, .. }
+        | Command::Extend { circuit, .. }
+        | Command::Verify { circuit, .. } => circuit.clone(),
+        // inspecting a handoff does not need the circuit
+        Command::Inspect { handoff, path } => return inspect(handoff, path),
+        Command::RelationshipSecret {
+            handoff,
+            auth_secret,
+        } => return relationship_secret(handoff, auth_secret),
+        Command::PathSalt {
+            handoff,
+            username,
+            auth_secret,
+        } => return path_salt(handoff, username, auth_secret),
+    };
+    match args.native_witness {
+        true => run(
+            command,
+            grapevine_native_circuit(args.r1cs.clone())?,
+            args.r1cs,
+        ),
+        false => run(
+            command,
+            grapevine_circuit(args.r1cs.clone(), args.wasm)?,
+            args.r1cs,
+        ),
+    }
+}
@@ -0,0 +1,88 @@
+use clap::Parser;
+use color_eyre::eyre::Result;
+use grapevine_sonobe::{
+    async_prover::{AsyncProver, AsyncProverConfig},
+    batch::ProverContext,
+    circom::grapevine_circuit,
+    envelope::Fingerprint,
+    params::deserialize_params,
+    service::{router, ServiceState},
+};
+use std::{fs, net::SocketAddr, path::PathBuf, time::Duration};
+
+#[derive(Parser)]
+#[command(
+    name = "grapevine_service",
+    about = "Serve grapevine proving and verification over http"
+)]
+struct Cli {
+    /// Path to the compiled grapevine r1cs
+    #[arg(long, default_value = "./circom/artifacts/grapevine.r1cs")]
+    r1cs: PathBuf,
+    /// Path to the compiled grapevine wasm
+    #[arg(long, default_value = "./circom/artifacts/grapevine.wasm")]
+    wasm: PathBuf,
+    /// Params written by `grapevine setup`, generated at startup if omitted
+    #[arg(long)]
+    params: Option<PathBuf>,
+    /// Address to listen on, localhost only by default since requests carry auth secrets
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    addr: SocketAddr,
+    /// Proving jobs running at once, defaults to the available parallelism
+    #[arg(long)]
+    max_concurrent_jobs: Option<usize>,
+    /// Proving jobs waiting for a slot before requests are rejected with 503
+    #[arg(long)]
+    max_queued_jobs: Option<usize>,
+    /// Seconds a job may take before it is cancelled and the request fails with 504
+    #[arg(long, default_value_t = 300)]
+    job_timeout: u64,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    color_eyre::install()?;
+    let cli = Cli::parse();
+    let defaults = AsyncProverConfig::default();
+    let config = AsyncProverConfig {
+        max_concurrent_jobs: cli
+            .max_concurrent_jobs
+            .unwrap_or(defaults.max_concurrent_jobs),
+        max_queued_jobs: cli.max_queued_jobs.unwrap_or(defaults.max_queued_jobs),
+        job_timeout: Duration::from_secs(cli.job_timeout),
+    };
+
+    let f_circuit = grapevine_circuit(cli.r1cs.clone(), cli.wasm)?;
+    let (prover, fingerprint) = match cli.params {
+        Some(params) => {
+            let bytes = fs::read(params)?;
+            let (prover_params, verifier_params) = deserialize_params(&bytes, f_circuit.clone())?;
+            let context = ProverContext {
+                prover_params,
+                f_circuit,
+            };
+            let fingerprint = Fingerprint::new(&fs::read(&cli.r1cs)?, &bytes);
+            (
+                AsyncProver::new(context, verifier_params, config),
+                fingerprint,
+            )
+        }
+        None => {
+            let prover = AsyncProver::setup(f_circuit, config).await?;
+            let fingerprint = Fingerprint::from_params(&cli.r1cs, &prover.context().prover_params)?;
+            (prover, fingerprint)
+        }
+    };
+
+    let listener = tokio::net::TcpListener::bind(cli.addr).await?;
+    println!("Listening on {}", listener.local_addr()?);
+    axum::serve(
+        listener,
+        router(ServiceState {
+            prover,
+            fingerprint,
+        }),
+    )
+    .await?;
+    Ok(())
+}
@@ -1,10 +1,104 @@
+use ark_bn254::Fr;
+use sonobe::frontend::{circom::CircomFCircuit, FCircuit};
+use std::{path::PathBuf, sync::Arc};
+
+use crate::errors::GrapevineError;
+use crate::utils::{
+    ANONYMOUS_EXTERNAL_INPUTS_LENGTH, EDDSA_EXTERNAL_INPUTS_LENGTH, EXTERNAL_INPUTS_LENGTH,
+    IVC_STATE_LENGTH,
+};
+use crate::witness::{CircomR1CS, NativeCircomFCircuit};
+
+/**
+ * Loads the compiled grapevine circuit as a sonobe F circuit
+ *
+ * @param r1cs_path - path to the compiled grapevine.r1cs
+ * @param wasm_path - path to the compiled grapevine.wasm
+ * @return - the grapevine F circuit
+ */
+pub fn grapevine_circuit(
+    r1cs_path: PathBuf,
+    wasm_path: PathBuf,
+) -> Result<CircomFCircuit<Fr>, GrapevineError> {
+    Ok(CircomFCircuit::<Fr>::new((
+        r1cs_path,
+        wasm_path,
+        IVC_STATE_LENGTH,
+        EXTERNAL_INPUTS_LENGTH,
+    ))?)
+}
+
+/**
+ * Loads the compiled eddsa grapevine circuit, where links are authorized by signatures
+ *
+ * @param r1cs_path - path to the compiled grapevine_eddsa.r1cs
+ * @param wasm_path - path to the compiled grapevine_eddsa.wasm
+ * @return - the eddsa grapevine F circuit
+ */
+pub fn grapevine_eddsa_circuit(
+    r1cs_path: PathBuf,
+    wasm_path: PathBuf,
+) -> Result<CircomFCircuit<Fr>, GrapevineError> {
+    Ok(CircomFCircuit::<Fr>::new((
+        r1cs_path,
+        wasm_path,
+        IVC_STATE_LENGTH,
+        EDDSA_EXTERNAL_INPUTS_LENGTH,
+    ))?)
+}
+
+/**
+ * Loads the compiled anonymous grapevine circuit, where degree 1 proves membership of the
+ * phrase in a public phrase set instead of revealing the phrase hash
+ *
+ * @param r1cs_path - path to the compiled grapevine_anonymous.r1cs
+ * @param wasm_path - path to the compiled grapevine_anonymous.wasm
+ * @return - the anonymous grapevine F circuit
+ */
+pub fn grapevine_anonymous_circuit(
+    r1cs_path: PathBuf,
+    wasm_path: PathBuf,
+) -> Result<CircomFCircuit<Fr>, GrapevineError> {
+    Ok(CircomFCircuit::<Fr>::new((
+        r1cs_path,
+        wasm_path,
+        IVC_STATE_LENGTH,
+        ANONYMOUS_EXTERNAL_INPUTS_LENGTH,
+    ))?)
+}
+
+/**
+ * Loads the compiled grapevine circuit with native witness generation instead of wasm
+ *
+ * @param r1cs_path - path to the compiled grapevine.r1cs
+ * @return - the grapevine F circuit
+ */
+pub fn grapevine_native_circuit(
+    r1cs_path: PathBuf,
+) -> Result<NativeCircomFCircuit<Fr>, GrapevineError> {
+    let r1cs = CircomR1CS::<Fr>::from_file(&r1cs_path)?;
+    Ok(NativeCircomFCircuit::<Fr>::new((
+        Arc::new(r1cs),
+        IVC_STATE_LENGTH,
+        EXTERNAL_INPUTS_LENGTH,
+    ))?)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::params::test_nova_setup;
+    use crate::utils::eddsa::{sign_link, PrivateKey, Signature};
+    use crate::utils::hash::pubkey_degree_secret_hash;
     use crate::utils::inputs::{
-        get_z0, prepare_external_inputs, random_f_bigint, CircomPrivateInput,
+        derive_relationship_secret, get_anonymous_z0, get_z0, hash_phrase,
+        prepare_anonymous_external_inputs, prepare_eddsa_external_inputs, prepare_external_inputs,
+        random_f_bigint, serialize_username, AnonymousPrivateInput, CircomPrivateInput,
+        EddsaPrivateInput,
     };
+    use crate::utils::path::{derive_path_salt, verify_membership, PathInsertion, PathTree};
+    use crate::utils::phrase_set::PhraseSet;
+    use crate::utils::SECRET_FIELD_LENGTH;
     use ark_bn254::{constraints::GVar, Fr, G1Projective as Projective};
     // use ark_circom::circom::CircomCircuit;
     use ark_ff::{BigInteger, PrimeField};
@@ -23,6 +117,13 @@ mod test {
     use std::time::Instant;
 
     use crate::errors::GrapevineError;
+    use crate::handoff::Handoff;
+    use crate::nova::{
+        extend_anonymous, extend_eddsa, prove_degree, prove_first_anonymous, prove_first_eddsa,
+        verify, verify_anonymous, NovaType,
+    };
+    use crate::params::{GrapevineProverParams, GrapevineVerifierParams};
+    use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
 
     lazy_static! {
         pub static ref R1CS_PATH: PathBuf = PathBuf::from("./circom/artifacts/grapevine.r1cs");
@@ -42,6 +143,23 @@ mod test {
             .unwrap();
     }
 
+    /// Path tree holding the first `degree` test users, each salted with its index
+    fn test_path_tree(degree: usize) -> PathTree {
+        let mut tree = PathTree::new();
+        for (i, username) in USERNAMES[..degree].iter().enumerate() {
+            tree.insert(username, Fr::from(i as u64)).unwrap();
+        }
+        tree
+    }
+
+    /// Insertion of the test user at `index` into the path tree of the users before it
+    fn test_path_insertion(index: usize) -> Option<PathInsertion> {
+        let insertion = test_path_tree(index)
+            .insert(&USERNAMES[index], Fr::from(index as u64))
+            .unwrap();
+        Some(insertion)
+    }
+
     // Converts a PrimeField element to a num_bigint::BigInt representation.
     pub fn ark_primefield_to_num_bigint<F: PrimeField>(value: F) -> BigInt {
         let primefield_bigint: F::BigInt = value.into_bigint();
@@ -57,11 +175,13 @@ mod test {
         let step_0_inputs = CircomPrivateInput {
             phrase: Some(String::from(&*PHRASE)),
             usernames: [None, Some(String::from(&*USERNAMES[0]))],
-            auth_secrets: [None, Some(AUTH_SECRETS[0].clone())],
+            relationship_secret: None,
+            auth_secret: Some(AUTH_SECRETS[0].clone()),
+            path: test_path_insertion(0),
             chaff: false,
         };
-        let external_inputs = prepare_external_inputs::<Fr>(&step_0_inputs);
-        let z_0 = get_z0::<Fr>();
+        let external_inputs = prepare_external_inputs::<Fr>(&step_0_inputs).unwrap();
+        let z_0 = get_z0();
         dbg!(&z_0);
 
         let z_0_bi = z_0
@@ -103,14 +223,21 @@ mod test {
         let step_0_inputs = CircomPrivateInput {
             phrase: Some(String::from(&*PHRASE)),
             usernames: [None, Some(String::from(&*USERNAMES[0]))],
-            auth_secrets: [None, Some(AUTH_SECRETS[0].clone())],
+            relationship_secret: None,
+            auth_secret: Some(AUTH_SECRETS[0].clone()),
+            path: test_path_insertion(0),
             chaff: false,
         };
-        let external_inputs = prepare_external_inputs::<Fr>(&step_0_inputs);
+        let external_inputs = prepare_external_inputs::<Fr>(&step_0_inputs).unwrap();
 
         // initialize new Grapevine function circuit
-        let f_circuit =
-            CircomFCircuit::<Fr>::new((R1CS_PATH.clone(), WASM_PATH.clone(), 4, 6 + 4)).unwrap(); // 4=ivc_input.lenght, 6+4=external_inputs
+        let f_circuit = CircomFCircuit::<Fr>::new((
+            R1CS_PATH.clone(),
+            WASM_PATH.clone(),
+            IVC_STATE_LENGTH,
+            EXTERNAL_INPUTS_LENGTH,
+        ))
+        .unwrap();
 
         let z_0 = get_z0();
         let z_1 = f_circuit
@@ -125,14 +252,21 @@ mod test {
         let step_0_inputs = CircomPrivateInput {
             phrase: Some(String::from(&*PHRASE)),
             usernames: [None, Some(String::from(&*USERNAMES[0]))],
-            auth_secrets: [None, Some(AUTH_SECRETS[0].clone())],
+            relationship_secret: None,
+            auth_secret: Some(AUTH_SECRETS[0].clone()),
+            path: test_path_insertion(0),
             chaff: false,
         };
-        let external_inputs = prepare_external_inputs::<Fr>(&step_0_inputs);
+        let external_inputs = prepare_external_inputs::<Fr>(&step_0_inputs).unwrap();
 
         // initialize new Grapevine function circuit
-        let f_circuit =
-            CircomFCircuit::<Fr>::new((R1CS_PATH.clone(), WASM_PATH.clone(), 4, 6 + 4)).unwrap(); // 4=ivc_input.lenght, 6+4=external_inputs
+        let f_circuit = CircomFCircuit::<Fr>::new((
+            R1CS_PATH.clone(),
+            WASM_PATH.clone(),
+            IVC_STATE_LENGTH,
+            EXTERNAL_INPUTS_LENGTH,
+        ))
+        .unwrap();
 
         let z_0 = get_z0();
         let z_1 = f_circuit
@@ -154,6 +288,697 @@ mod test {
         assert_eq!(z_1_var.value().unwrap(), z_1);
     }
 
+    #[test]
+    fn test_native_witness() {
+        let step_0_inputs = CircomPrivateInput {
+            phrase: Some(String::from(&*PHRASE)),
+            usernames: [None, Some(String::from(&*USERNAMES[0]))],
+            relationship_secret: None,
+            auth_secret: Some(AUTH_SECRETS[0].clone()),
+            path: test_path_insertion(0),
+            chaff: false,
+        };
+        let external_inputs = prepare_external_inputs::<Fr>(&step_0_inputs).unwrap();
+        let wasm_circuit = grapevine_circuit(R1CS_PATH.clone(), WASM_PATH.clone()).unwrap();
+        let native_circuit = grapevine_native_circuit(R1CS_PATH.clone()).unwrap();
+
+        // compare the logic step and the following chaff step
+        let z_0 = get_z0().to_vec();
+        let z_1 = wasm_circuit
+            .step_native(0, z_0.clone(), external_inputs.clone())
+            .unwrap();
+        assert_eq!(
+            native_circuit
+                .step_native(0, z_0.clone(), external_inputs.clone())
+                .unwrap(),
+            z_1
+        );
+        let chaff_inputs = prepare_external_inputs::<Fr>(&CircomPrivateInput::empty(true)).unwrap();
+        assert_eq!(
+            native_circuit
+                .step_native(1, z_1.clone(), chaff_inputs.clone())
+                .unwrap(),
+            wasm_circuit.step_native(1, z_1, chaff_inputs).unwrap()
+        );
+
+        // the natively generated constraints are satisfied by the native witness
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let z_0_var = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_0)).unwrap();
+        let external_inputs_var =
+            Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(external_inputs)).unwrap();
+        native_circuit
+            .generate_step_constraints(cs.clone(), 0, z_0_var, external_inputs_var)
+            .unwrap();
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    /// F circuit synthesizing witnesses without checking them, optionally tampering wires
+    #[derive(Clone, Debug)]
+    struct ForgedFCircuit {
+        native: NativeCircomFCircuit<Fr>,
+        tamper: Option<fn(&mut Vec<Fr>)>,
+    }
+
+    impl ForgedFCircuit {
+        fn witness(&self, z_i: &[Fr], external_inputs: &[Fr]) -> Vec<Fr> {
+            let mut witness = self
+                .native
+                .solver
+                .solve_unchecked(&[z_i, external_inputs].concat());
+            if let Some(tamper) = self.tamper {
+                tamper(&mut witness);
+            }
+            witness
+        }
+    }
+
+    impl FCircuit<Fr> for ForgedFCircuit {
+        type Params = NativeCircomFCircuit<Fr>;
+
+        fn new(native: Self::Params) -> Result<Self, SonobeError> {
+            Ok(Self {
+                native,
+                tamper: None,
+            })
+        }
+
+        fn state_len(&self) -> usize {
+            self.native.state_len()
+        }
+
+        fn external_inputs_len(&self) -> usize {
+            self.native.external_inputs_len()
+        }
+
+        fn step_native(
+            &self,
+            _i: usize,
+            z_i: Vec<Fr>,
+            external_inputs: Vec<Fr>,
+        ) -> Result<Vec<Fr>, SonobeError> {
+            Ok(self.witness(&z_i, &external_inputs)[1..1 + IVC_STATE_LENGTH].to_vec())
+        }
+
+        fn generate_step_constraints(
+            &self,
+            cs: ConstraintSystemRef<Fr>,
+            _i: usize,
+            z_i: Vec<FpVar<Fr>>,
+            external_inputs: Vec<FpVar<Fr>>,
+        ) -> Result<Vec<FpVar<Fr>>, SynthesisError> {
+            let values = z_i
+                .iter()
+                .chain(external_inputs.iter())
+                .map(|var| var.value())
+                .collect::<Result<Vec<Fr>, SynthesisError>>();
+            let witness = values
+                .ok()
+                .map(|v| self.witness(&v[..IVC_STATE_LENGTH], &v[IVC_STATE_LENGTH..]));
+            self.native
+                .synthesize(cs, witness.as_deref(), &z_i, &external_inputs)
+        }
+    }
+
+    lazy_static! {
+        static ref FORGED_CIRCUIT: ForgedFCircuit =
+            ForgedFCircuit::new(grapevine_native_circuit(R1CS_PATH.clone()).unwrap()).unwrap();
+        static ref PARAMS: (GrapevineProverParams, GrapevineVerifierParams) =
+            test_nova_setup(FORGED_CIRCUIT.clone());
+    }
+
+    fn degree_1_inputs() -> CircomPrivateInput {
+        CircomPrivateInput {
+            phrase: Some(String::from(&*PHRASE)),
+            usernames: [None, Some(String::from(&*USERNAMES[0]))],
+            relationship_secret: None,
+            auth_secret: Some(AUTH_SECRETS[0].clone()),
+            path: test_path_insertion(0),
+            chaff: false,
+        }
+    }
+
+    fn degree_2_inputs(
+        prev_username: &str,
+        prev_relationship_secret: BigInt,
+    ) -> CircomPrivateInput {
+        CircomPrivateInput {
+            phrase: None,
+            usernames: [
+                Some(String::from(prev_username)),
+                Some(String::from(&*USERNAMES[1])),
+            ],
+            relationship_secret: Some(prev_relationship_secret),
+            auth_secret: Some(AUTH_SECRETS[1].clone()),
+            path: test_path_insertion(1),
+            chaff: false,
+        }
+    }
+
+    /// Relationship secret handed to degree 2 by the degree 1 user
+    fn degree_1_relationship_secret() -> BigInt {
+        derive_relationship_secret(&AUTH_SECRETS[0], degree_1_state()[1])
+    }
+
+    /// Honest state after degree 1 (logic and chaff steps)
+    fn degree_1_state() -> Vec<Fr> {
+        let z_1 = FORGED_CIRCUIT
+            .step_native(
+                0,
+                get_z0().to_vec(),
+                prepare_external_inputs(&degree_1_inputs()).unwrap(),
+            )
+            .unwrap();
+        let chaff = prepare_external_inputs(&CircomPrivateInput::empty(true)).unwrap();
+        FORGED_CIRCUIT.step_native(1, z_1, chaff).unwrap()
+    }
+
+    /// Checks that a step leaves the constraint system of the grapevine circuit unsatisfied
+    fn assert_step_unsatisfied(
+        z_i: Vec<Fr>,
+        inputs: &CircomPrivateInput,
+        tamper: Option<fn(&mut Vec<Fr>)>,
+    ) {
+        let f_circuit = ForgedFCircuit {
+            tamper,
+            ..FORGED_CIRCUIT.clone()
+        };
+        let external_inputs = prepare_external_inputs::<Fr>(inputs).unwrap();
+        // the checked witness generation refuses the step outright
+        if tamper.is_none() {
+            let circuit_inputs = [z_i.clone(), external_inputs.clone()].concat();
+            assert!(f_circuit.native.solver.solve(&circuit_inputs).is_err());
+        }
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let z_i_var = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i)).unwrap();
+        let external_inputs_var =
+            Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(external_inputs)).unwrap();
+        f_circuit
+            .generate_step_constraints(cs.clone(), 2, z_i_var, external_inputs_var)
+            .unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    /// Checks that folding a forged step after an honest degree 1 fails verification
+    fn assert_fold_rejected(
+        tamper_state: fn(&mut Vec<Fr>),
+        inputs: &CircomPrivateInput,
+        tamper: Option<fn(&mut Vec<Fr>)>,
+    ) {
+        let (prover_params, verifier_params) = &*PARAMS;
+        let mut nova = NovaType::<ForgedFCircuit>::init(
+            prover_params,
+            FORGED_CIRCUIT.clone(),
+            get_z0().to_vec(),
+        )
+        .unwrap();
+        prove_degree(&mut nova, &degree_1_inputs()).unwrap();
+        let handoff = Handoff::from_nova(&nova, test_path_tree(1));
+        verify::<ForgedFCircuit>(verifier_params, &handoff).unwrap();
+
+        tamper_state(&mut nova.z_i);
+        nova.F.tamper = tamper;
+        // the forged circuit synthesizes the witness unchecked, so sonobe folds the step and it is
+        // up to the verifier to reject the proof
+        nova.prove_step(prepare_external_inputs(inputs).unwrap())
+            .unwrap();
+        let handoff = Handoff::from_nova(&nova, test_path_tree(1));
+        assert!(verify::<ForgedFCircuit>(verifier_params, &handoff).is_err());
+    }
+
+    #[test]
+    fn test_rejects_forged_z_0() {
+        // fold an honest degree 1 from a start state with a preset path accumulator, which the
+        // circuit carries forward without checking
+        let (prover_params, verifier_params) = &*PARAMS;
+        let mut z_0 = get_z0().to_vec();
+        z_0[4] = Fr::from(42);
+        let mut nova =
+            NovaType::<ForgedFCircuit>::init(prover_params, FORGED_CIRCUIT.clone(), z_0).unwrap();
+        prove_degree(&mut nova, &degree_1_inputs()).unwrap();
+        let handoff = Handoff::from_nova(&nova, test_path_tree(1));
+        let result = verify::<ForgedFCircuit>(verifier_params, &handoff);
+        assert!(matches!(result, Err(GrapevineError::Sonobe(msg)) if msg.contains("initial")));
+    }
+
+    #[test]
+    fn test_rejects_wrong_relationship_secret() {
+        let inputs = degree_2_inputs(&USERNAMES[0], random_f_bigint::<Fr>());
+        assert_step_unsatisfied(degree_1_state(), &inputs, None);
+        assert_fold_rejected(|_| {}, &inputs, None);
+    }
+
+    #[test]
+    fn test_rejects_raw_auth_secret() {
+        // the previous user's auth secret no longer opens the degree secret hash
+        let inputs = degree_2_inputs(&USERNAMES[0], AUTH_SECRETS[0].clone());
+        assert_step_unsatisfied(degree_1_state(), &inputs, None);
+        assert_fold_rejected(|_| {}, &inputs, None);
+    }
+
+    #[test]
+    fn test_rejects_relationship_secret_of_other_phrase() {
+        let other_phrase_hash = Fr::from(1);
+        let secret = derive_relationship_secret(&AUTH_SECRETS[0], other_phrase_hash);
+        let inputs = degree_2_inputs(&USERNAMES[0], secret);
+        assert_step_unsatisfied(degree_1_state(), &inputs, None);
+    }
+
+    #[test]
+    fn test_rejects_wrong_previous_username() {
+        let inputs = degree_2_inputs("mallory", degree_1_relationship_secret());
+        assert_step_unsatisfied(degree_1_state(), &inputs, None);
+        assert_fold_rejected(|_| {}, &inputs, None);
+    }
+
+    #[test]
+    fn test_rejects_tampered_degree_secret_hash() {
+        let inputs = degree_2_inputs(&USERNAMES[0], degree_1_relationship_secret());
+        let tamper: fn(&mut Vec<Fr>) = |z_i| z_i[2] += Fr::from(1);
+        let mut z_i = degree_1_state();
+        tamper(&mut z_i);
+        assert_step_unsatisfied(z_i, &inputs, None);
+        assert_fold_rejected(tamper, &inputs, None);
+    }
+
+    #[test]
+    fn test_rejects_non_boolean_chaff() {
+        let inputs = degree_2_inputs(&USERNAMES[0], degree_1_relationship_secret());
+        let tamper: fn(&mut Vec<Fr>) = |z_i| z_i[3] = Fr::from(2);
+        let mut z_i = degree_1_state();
+        tamper(&mut z_i);
+        assert_step_unsatisfied(z_i, &inputs, None);
+        assert_fold_rejected(tamper, &inputs, None);
+    }
+
+    #[test]
+    fn test_rejects_degree_reset() {
+        // claim degree 0 as the output of an otherwise honest degree 2 step
+        let inputs = degree_2_inputs(&USERNAMES[0], degree_1_relationship_secret());
+        let reset: fn(&mut Vec<Fr>) = |witness| witness[1] = Fr::from(0);
+        assert_step_unsatisfied(degree_1_state(), &inputs, Some(reset));
+        assert_fold_rejected(|_| {}, &inputs, Some(reset));
+    }
+
+    #[test]
+    fn test_rejects_stale_path_siblings() {
+        // siblings of the empty tree do not open bob's slot once alice is inserted
+        let inputs = CircomPrivateInput {
+            path: test_path_insertion(0),
+            ..degree_2_inputs(&USERNAMES[0], degree_1_relationship_secret())
+        };
+        assert_step_unsatisfied(degree_1_state(), &inputs, None);
+        assert_fold_rejected(|_| {}, &inputs, None);
+    }
+
+    #[test]
+    fn test_path_root() {
+        let z_i = degree_1_state();
+        assert_eq!(z_i[5], test_path_tree(1).root());
+        let inputs = degree_2_inputs(&USERNAMES[0], degree_1_relationship_secret());
+        let z_i = FORGED_CIRCUIT
+            .step_native(2, z_i, prepare_external_inputs(&inputs).unwrap())
+            .unwrap();
+        assert_eq!(z_i[5], test_path_tree(2).root());
+    }
+
+    #[test]
+    fn test_rejects_zero_usernames() {
+        // the input layer refuses empty usernames
+        let inputs = degree_2_inputs("", degree_1_relationship_secret());
+        assert!(prepare_external_inputs::<Fr>(&inputs).is_err());
+        let inputs = CircomPrivateInput {
+            usernames: [None, Some(String::from(""))],
+            ..degree_1_inputs()
+        };
+        assert!(prepare_external_inputs::<Fr>(&inputs).is_err());
+
+        // and the circuit refuses zero usernames set directly in the external inputs
+        let prev_username = SECRET_FIELD_LENGTH;
+        let cases = [
+            // zero current username at degree 0
+            (
+                get_z0().to_vec(),
+                degree_1_inputs(),
+                prev_username + 1,
+                Fr::from(0),
+            ),
+            // non-zero previous username at degree 0
+            (
+                get_z0().to_vec(),
+                degree_1_inputs(),
+                prev_username,
+                Fr::from(1),
+            ),
+            // zero current username past degree 0
+            (
+                degree_1_state(),
+                degree_2_inputs(&USERNAMES[0], degree_1_relationship_secret()),
+                prev_username + 1,
+                Fr::from(0),
+            ),
+        ];
+        for (z_i, inputs, index, value) in cases {
+            let mut external_inputs = prepare_external_inputs::<Fr>(&inputs).unwrap();
+            external_inputs[index] = value;
+            let circuit_inputs = [z_i, external_inputs].concat();
+            assert!(FORGED_CIRCUIT.native.solver.solve(&circuit_inputs).is_err());
+        }
+
+        // chaff steps carry random usernames and are not checked
+        let mut chaff = prepare_external_inputs::<Fr>(&CircomPrivateInput::empty(true)).unwrap();
+        chaff[prev_username + 1] = Fr::from(0);
+        assert!(FORGED_CIRCUIT
+            .step_native(1, degree_1_state(), chaff)
+            .is_ok());
+    }
+
+    lazy_static! {
+        static ref EDDSA_CIRCUIT: CircomFCircuit<Fr> = grapevine_eddsa_circuit(
+            PathBuf::from("./circom/artifacts/grapevine_eddsa.r1cs"),
+            PathBuf::from("./circom/artifacts/grapevine_eddsa.wasm"),
+        )
+        .unwrap();
+        static ref PRIVATE_KEYS: [PrivateKey; 3] = [
+            PrivateKey::random(),
+            PrivateKey::random(),
+            PrivateKey::random()
+        ];
+    }
+
+    /// Runs a step of the eddsa circuit and checks its constraints are satisfied
+    fn eddsa_step(
+        i: usize,
+        z_i: Vec<Fr>,
+        inputs: &EddsaPrivateInput,
+    ) -> Result<Vec<Fr>, SonobeError> {
+        let external_inputs = prepare_eddsa_external_inputs(inputs).unwrap();
+        let z_i1 = EDDSA_CIRCUIT.step_native(i, z_i.clone(), external_inputs.clone())?;
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let z_i_var = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i)).unwrap();
+        let external_inputs_var =
+            Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(external_inputs)).unwrap();
+        let z_i1_var = EDDSA_CIRCUIT
+            .generate_step_constraints(cs.clone(), i, z_i_var, external_inputs_var)
+            .unwrap();
+        assert_eq!(z_i1_var.value().unwrap(), z_i1);
+        assert!(cs.is_satisfied().unwrap());
+        Ok(z_i1)
+    }
+
+    /// Honest state of the eddsa circuit after degree 1 (logic and chaff steps)
+    fn eddsa_degree_1_state() -> Vec<Fr> {
+        let inputs = EddsaPrivateInput {
+            phrase: Some(String::from(&*PHRASE)),
+            usernames: [None, Some(String::from(&*USERNAMES[0]))],
+            prev_public_key: None,
+            link_signature: None,
+            public_key: Some(PRIVATE_KEYS[0].public_key()),
+            path: test_path_insertion(0),
+            chaff: false,
+        };
+        let z_1 = eddsa_step(0, get_z0().to_vec(), &inputs).unwrap();
+        eddsa_step(1, z_1, &EddsaPrivateInput::empty(true)).unwrap()
+    }
+
+    fn eddsa_degree_2_inputs(link_signature: Signature) -> EddsaPrivateInput {
+        EddsaPrivateInput {
+            phrase: None,
+            usernames: [
+                Some(String::from(&*USERNAMES[0])),
+                Some(String::from(&*USERNAMES[1])),
+            ],
+            prev_public_key: Some(PRIVATE_KEYS[0].public_key()),
+            link_signature: Some(link_signature),
+            public_key: Some(PRIVATE_KEYS[1].public_key()),
+            path: test_path_insertion(1),
+            chaff: false,
+        }
+    }
+
+    #[test]
+    fn test_eddsa_degrees() {
+        let z_i = eddsa_degree_1_state();
+        assert_eq!(z_i[0], Fr::from(1));
+
+        // alice signs the link to bob, who proves degree 2
+        let signature = sign_link(
+            &PRIVATE_KEYS[0],
+            &USERNAMES[1],
+            &PRIVATE_KEYS[1].public_key(),
+            z_i[1],
+        )
+        .unwrap();
+        let z_i = eddsa_step(2, z_i, &eddsa_degree_2_inputs(signature)).unwrap();
+        let z_i = eddsa_step(3, z_i, &EddsaPrivateInput::empty(true)).unwrap();
+        assert_eq!(z_i[0], Fr::from(2));
+        let username = Fr::from(
+            serialize_username(&USERNAMES[1])
+                .unwrap()
+                .to_biguint()
+                .unwrap(),
+        );
+        assert_eq!(
+            z_i[2],
+            pubkey_degree_secret_hash(z_i[1], username, &PRIVATE_KEYS[1].public_key())
+        );
+    }
+
+    #[test]
+    fn test_eddsa_rejects_forged_link() {
+        let z_i = eddsa_degree_1_state();
+        // signed by a key other than alice's
+        let signature = sign_link(
+            &PRIVATE_KEYS[2],
+            &USERNAMES[1],
+            &PRIVATE_KEYS[1].public_key(),
+            z_i[1],
+        )
+        .unwrap();
+        let inputs = eddsa_degree_2_inputs(signature);
+        assert!(EDDSA_CIRCUIT
+            .step_native(
+                2,
+                z_i.clone(),
+                prepare_eddsa_external_inputs(&inputs).unwrap()
+            )
+            .is_err());
+        // signed by alice for a link to another user
+        let signature = sign_link(
+            &PRIVATE_KEYS[0],
+            &USERNAMES[2],
+            &PRIVATE_KEYS[1].public_key(),
+            z_i[1],
+        )
+        .unwrap();
+        let inputs = eddsa_degree_2_inputs(signature);
+        assert!(EDDSA_CIRCUIT
+            .step_native(
+                2,
+                z_i.clone(),
+                prepare_eddsa_external_inputs(&inputs).unwrap()
+            )
+            .is_err());
+        // signed by alice for a link to bob proving with another key
+        let signature = sign_link(
+            &PRIVATE_KEYS[0],
+            &USERNAMES[1],
+            &PRIVATE_KEYS[2].public_key(),
+            z_i[1],
+        )
+        .unwrap();
+        let inputs = eddsa_degree_2_inputs(signature);
+        assert!(EDDSA_CIRCUIT
+            .step_native(
+                2,
+                z_i.clone(),
+                prepare_eddsa_external_inputs(&inputs).unwrap()
+            )
+            .is_err());
+        // signed by alice for the same link in another chain
+        let signature = sign_link(
+            &PRIVATE_KEYS[0],
+            &USERNAMES[1],
+            &PRIVATE_KEYS[1].public_key(),
+            Fr::from(1),
+        )
+        .unwrap();
+        let inputs = eddsa_degree_2_inputs(signature);
+        assert!(EDDSA_CIRCUIT
+            .step_native(2, z_i, prepare_eddsa_external_inputs(&inputs).unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn test_eddsa_chain() {
+        let (prover_params, verifier_params) = test_nova_setup(EDDSA_CIRCUIT.clone());
+        let handoff = prove_first_eddsa(
+            &prover_params,
+            EDDSA_CIRCUIT.clone(),
+            String::from(&*PHRASE),
+            String::from(&*USERNAMES[0]),
+            &PRIVATE_KEYS[0],
+        )
+        .unwrap();
+        verify::<CircomFCircuit<Fr>>(&verifier_params, &handoff).unwrap();
+
+        let signature = sign_link(
+            &PRIVATE_KEYS[0],
+            &USERNAMES[1],
+            &PRIVATE_KEYS[1].public_key(),
+            handoff.z_i[1],
+        )
+        .unwrap();
+        let handoff = extend_eddsa(
+            &prover_params,
+            EDDSA_CIRCUIT.clone(),
+            handoff,
+            String::from(&*USERNAMES[0]),
+            PRIVATE_KEYS[0].public_key(),
+            signature,
+            String::from(&*USERNAMES[1]),
+            &PRIVATE_KEYS[1],
+        )
+        .unwrap();
+        verify::<CircomFCircuit<Fr>>(&verifier_params, &handoff).unwrap();
+        assert_eq!(handoff.z_i[0], Fr::from(2));
+
+        // bob discloses that he is on the path without revealing alice
+        let secret = BigInt::from(PRIVATE_KEYS[1].scalar().clone());
+        let salt = derive_path_salt(&secret, handoff.z_i[1], &USERNAMES[1]).unwrap();
+        let proof = handoff.path.prove_membership(&USERNAMES[1], salt).unwrap();
+        assert_eq!(proof.index, 1);
+        verify_membership(&handoff.z_i, &proof).unwrap();
+    }
+
+    lazy_static! {
+        static ref ANONYMOUS_CIRCUIT: CircomFCircuit<Fr> = grapevine_anonymous_circuit(
+            PathBuf::from("./circom/artifacts/grapevine_anonymous.r1cs"),
+            PathBuf::from("./circom/artifacts/grapevine_anonymous.wasm"),
+        )
+        .unwrap();
+        static ref PHRASE_SET: PhraseSet = PhraseSet::from_phrases(&[
+            String::from("Another registered phrase"),
+            String::from(&*PHRASE),
+            String::from("Yet another registered phrase"),
+        ])
+        .unwrap();
+    }
+
+    fn anonymous_degree_1_inputs() -> AnonymousPrivateInput {
+        let phrase_hash = hash_phrase(&PHRASE).unwrap();
+        AnonymousPrivateInput {
+            base: degree_1_inputs(),
+            phrase_hash: None,
+            phrase_witness: PHRASE_SET.witness(phrase_hash),
+        }
+    }
+
+    fn anonymous_step(i: usize, z_i: Vec<Fr>, inputs: &AnonymousPrivateInput) -> Vec<Fr> {
+        ANONYMOUS_CIRCUIT
+            .step_native(i, z_i, prepare_anonymous_external_inputs(inputs).unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_anonymous_degrees() {
+        let z_0 = get_anonymous_z0(PHRASE_SET.root()).to_vec();
+        let z_1 = anonymous_step(0, z_0, &anonymous_degree_1_inputs());
+        let z_i = anonymous_step(1, z_1, &AnonymousPrivateInput::empty(true));
+
+        // the state carries the phrase set root instead of the phrase hash
+        let phrase_hash = hash_phrase(&PHRASE).unwrap();
+        assert_eq!(z_i[0], Fr::from(1));
+        assert_eq!(z_i[1], PHRASE_SET.root());
+        assert!(!z_i.contains(&phrase_hash));
+
+        // bob extends the chain with the phrase hash alice shared with him
+        let relationship_secret = derive_relationship_secret(&AUTH_SECRETS[0], phrase_hash);
+        let inputs = AnonymousPrivateInput {
+            base: degree_2_inputs(&USERNAMES[0], relationship_secret),
+            phrase_hash: Some(phrase_hash),
+            phrase_witness: None,
+        };
+        let z_2 = anonymous_step(2, z_i.clone(), &inputs);
+        assert_eq!(z_2[0], Fr::from(2));
+        assert_eq!(z_2[1], PHRASE_SET.root());
+
+        // another registered phrase hash does not open alice's degree secret hash
+        let other_phrase_hash = PHRASE_SET.leaves[0];
+        let forged = AnonymousPrivateInput {
+            phrase_hash: Some(other_phrase_hash),
+            ..inputs
+        };
+        assert!(ANONYMOUS_CIRCUIT
+            .step_native(2, z_i, prepare_anonymous_external_inputs(&forged).unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn test_anonymous_rejects_unregistered_phrase() {
+        // the phrase is not registered in this set, so no witness opens its root
+        let phrase_set = PhraseSet::from_phrases(&[String::from("Another phrase")]).unwrap();
+        let z_0 = get_anonymous_z0(phrase_set.root()).to_vec();
+        let inputs = prepare_anonymous_external_inputs(&anonymous_degree_1_inputs()).unwrap();
+        assert!(ANONYMOUS_CIRCUIT.step_native(0, z_0, inputs).is_err());
+    }
+
+    #[test]
+    fn test_anonymous_chain() {
+        let (prover_params, verifier_params) = test_nova_setup(ANONYMOUS_CIRCUIT.clone());
+        let handoff = prove_first_anonymous(
+            &prover_params,
+            ANONYMOUS_CIRCUIT.clone(),
+            &PHRASE_SET,
+            String::from(&*PHRASE),
+            String::from(&*USERNAMES[0]),
+            AUTH_SECRETS[0].clone(),
+        )
+        .unwrap();
+        verify_anonymous::<CircomFCircuit<Fr>>(&verifier_params, &handoff, PHRASE_SET.root())
+            .unwrap();
+
+        let phrase_hash = hash_phrase(&PHRASE).unwrap();
+        let handoff = extend_anonymous(
+            &prover_params,
+            ANONYMOUS_CIRCUIT.clone(),
+            handoff,
+            phrase_hash,
+            String::from(&*USERNAMES[0]),
+            derive_relationship_secret(&AUTH_SECRETS[0], phrase_hash),
+            String::from(&*USERNAMES[1]),
+            AUTH_SECRETS[1].clone(),
+        )
+        .unwrap();
+        verify_anonymous::<CircomFCircuit<Fr>>(&verifier_params, &handoff, PHRASE_SET.root())
+            .unwrap();
+        assert_eq!(handoff.z_i[0], Fr::from(2));
+
+        // the proof does not verify against another phrase set
+        let other_root = PhraseSet::new().root();
+        assert!(
+            verify_anonymous::<CircomFCircuit<Fr>>(&verifier_params, &handoff, other_root).is_err()
+        );
+    }
+
+    #[test]
+    fn test_shape_step_has_witness() {
+        // sonobe's get_r1cs synthesizes each circuit over an all-zero state and external inputs
+        let z_i = vec![Fr::from(0); IVC_STATE_LENGTH];
+        let grapevine = grapevine_circuit(R1CS_PATH.clone(), WASM_PATH.clone()).unwrap();
+        let circuits = [
+            (&grapevine, EXTERNAL_INPUTS_LENGTH),
+            (&*EDDSA_CIRCUIT, EDDSA_EXTERNAL_INPUTS_LENGTH),
+            (&*ANONYMOUS_CIRCUIT, ANONYMOUS_EXTERNAL_INPUTS_LENGTH),
+        ];
+        for (circuit, external_inputs_len) in circuits {
+            let external_inputs = vec![Fr::from(0); external_inputs_len];
+            assert!(circuit.step_native(0, z_i.clone(), external_inputs).is_ok());
+        }
+        let external_inputs = vec![Fr::from(0); EXTERNAL_INPUTS_LENGTH];
+        let shape_step = [z_i, external_inputs].concat();
+        assert!(FORGED_CIRCUIT.native.solver.solve(&shape_step).is_ok());
+    }
+
     // WIP
     /*
     #[test]
@@ -166,7 +991,8 @@ mod test {
         let inputs = CircomPrivateInput {
             phrase: Some(String::from(&*PHRASE)),
             usernames: [None, Some(String::from(&*USERNAMES[0]))],
-            auth_secrets: [None, Some(AUTH_SECRETS[0].clone())],
+            relationship_secret: None,
+            auth_secret: Some(AUTH_SECRETS[0].clone()),
             chaff: false,
         };
         f_circuit.set_private_input(inputs);
@@ -245,7 +1071,8 @@ mod test {
     //     let inputs = CircomPrivateInput {
     //         phrase: Some(String::from(&*PHRASE)),
     //         usernames: [None, Some(String::from(&*USERNAMES[0]))],
-    //         auth_secrets: [None, Some(AUTH_SECRETS[0].clone())],
+    //         relationship_secret: None,
+    //         auth_secret: Some(AUTH_SECRETS[0].clone()),
     //         chaff: false
     //     };
     //     f_circuit.set_private_input(inputs);
@@ -273,7 +1100,8 @@ mod test {
     //     let step_0_inputs = CircomPrivateInput {
     //         phrase: Some(String::from(&*PHRASE)),
     //         usernames: [None, Some(String::from(&*USERNAMES[0]))],
-    //         auth_secrets: [None, Some(AUTH_SECRETS[0].clone())],
+    //         relationship_secret: None,
+    //         auth_secret: Some(AUTH_SECRETS[0].clone()),
     //         chaff: false
     //     };
     //
@@ -298,7 +1126,8 @@ mod test {
         let step_0_inputs = CircomPrivateInput {
             phrase: Some(String::from(&*PHRASE)),
             usernames: [None, Some(String::from(&*USERNAMES[0]))],
-            auth_secrets: [None, Some(AUTH_SECRETS[0].clone())],
+            relationship_secret: None,
+            auth_secret: Some(AUTH_SECRETS[0].clone()),
             chaff: false,
         };
         // let z_0 = get_z0();
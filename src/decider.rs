@@ -0,0 +1,299 @@
+use ark_bn254::{constraints::GVar, Bn254, Fr, G1Projective as Projective};
+use ark_groth16::Groth16;
+use ark_grumpkin::{constraints::GVar as GVar2, Projective as Projective2};
+use ark_std::rand::{CryptoRng, RngCore};
+use solidity_verifiers::{
+    utils::get_function_selector_for_nova_cyclefold_verifier,
+    verifiers::nova_cyclefold::get_decider_template_for_cyclefold_decider,
+    NovaCycleFoldVerifierKey,
+};
+use sonobe::{
+    commitment::{kzg::KZG, pedersen::Pedersen},
+    folding::nova::decider_eth::{prepare_calldata, Decider as DeciderEth},
+    frontend::FCircuit,
+    Decider,
+};
+
+use crate::errors::GrapevineError;
+use crate::handoff::Handoff;
+use crate::nova::NovaType;
+use crate::params::{GrapevineKzgProverParams, GrapevineVerifierParams};
+use crate::utils::inputs::get_z0;
+
+/** Nova committing with kzg on bn254, the only flavour of nova the eth decider can compress */
+pub type KzgNovaType<FC> = NovaType<FC, KZG<'static, Bn254>>;
+
+/** Decider compressing a nova proof into a groth16 proof verifiable on-chain */
+pub type DeciderType<FC> = DeciderEth<
+    Projective,
+    GVar,
+    Projective2,
+    GVar2,
+    FC,
+    KZG<'static, Bn254>,
+    Pedersen<Projective2>,
+    Groth16<Bn254>,
+    KzgNovaType<FC>,
+>;
+
+pub type DeciderProverParams<FC> =
+    <DeciderType<FC> as Decider<Projective, Projective2, FC, KzgNovaType<FC>>>::ProverParam;
+pub type DeciderVerifierParams<FC> =
+    <DeciderType<FC> as Decider<Projective, Projective2, FC, KzgNovaType<FC>>>::VerifierParam;
+pub type DeciderProof<FC> =
+    <DeciderType<FC> as Decider<Projective, Projective2, FC, KzgNovaType<FC>>>::Proof;
+
+/**
+ * Generates the groth16 keys of the decider circuit for a folding scheme
+ * @dev the decider circuit depends on the shape of the folding scheme but not on its state, so
+ *      any nova instance of the grapevine circuit can be used
+ *
+ * @param rng - the randomness of the groth16 setup
+ * @param prover_params - the kzg prover params of the folding scheme
+ * @param verifier_params - the verifier params of the folding scheme
+ * @param nova - a folding scheme for the circuit to decide
+ * @return - the decider prover and verifier params
+ */
+pub fn decider_setup<FC, R>(
+    rng: &mut R,
+    prover_params: GrapevineKzgProverParams,
+    verifier_params: GrapevineVerifierParams,
+    nova: KzgNovaType<FC>,
+) -> Result<(DeciderProverParams<FC>, DeciderVerifierParams<FC>), GrapevineError>
+where
+    FC: FCircuit<Fr>,
+    R: RngCore + CryptoRng,
+{
+    Ok(DeciderType::<FC>::preprocess(
+        rng,
+        &(prover_params, verifier_params),
+        nova,
+    )?)
+}
+
+/**
+ * Compresses the ivc proof of a folding scheme into a decider proof
+ *
+ * @param rng - the randomness of the groth16 and kzg proofs
+ * @param prover_params - the decider prover params
+ * @param nova - the folding scheme to compress the state of
+ * @return - the decider proof
+ */
+pub fn prove_decider<FC, R>(
+    rng: R,
+    prover_params: DeciderProverParams<FC>,
+    nova: KzgNovaType<FC>,
+) -> Result<DeciderProof<FC>, GrapevineError>
+where
+    FC: FCircuit<Fr>,
+    R: RngCore + CryptoRng,
+{
+    Ok(DeciderType::<FC>::prove(rng, prover_params, nova)?)
+}
+
+/**
+ * Verifies a decider proof natively against the instances of a handoff
+ *
+ * @param verifier_params - the decider verifier params
+ * @param handoff - the handoff the decider proof was generated for
+ * @param proof - the decider proof
+ */
+pub fn verify_decider<FC: FCircuit<Fr>>(
+    verifier_params: DeciderVerifierParams<FC>,
+    handoff: &Handoff,
+    proof: &DeciderProof<FC>,
+) -> Result<(), GrapevineError> {
+    // as in `nova::verify`, the proof says nothing about where the chain started
+    if handoff.z_0 != get_z0() {
+        return Err(GrapevineError::Sonobe(String::from(
+            "chain does not start from the initial ivc state",
+        )));
+    }
+    let verified = DeciderType::<FC>::verify(
+        verifier_params,
+        handoff.i,
+        handoff.z_0.clone(),
+        handoff.z_i.clone(),
+        &handoff.big_u_i.clone().into(),
+        &handoff.u_i.clone().into(),
+        proof,
+    )?;
+    match verified {
+        true => Ok(()),
+        false => Err(GrapevineError::Sonobe(String::from(
+            "decider proof did not verify",
+        ))),
+    }
+}
+
+/**
+ * Generates the solidity contracts verifying decider proofs
+ * @dev the `NovaDecider` contract generated by sonobe reads z_0 from the calldata and accepts
+ *      chains from any start state, so it is wrapped by `GrapevineDecider`, which only forwards
+ *      calls whose z_0 is the given initial state. `GrapevineDecider` is the contract to deploy
+ *
+ * @param verifier_params - the decider verifier params
+ * @param z_0 - the initial ivc state of the circuit, e.g. `get_z0()`
+ * @return - the source of the `NovaDecider` and `GrapevineDecider` contracts
+ */
+pub fn solidity_verifier<FC: FCircuit<Fr>>(
+    verifier_params: DeciderVerifierParams<FC>,
+    z_0: &[Fr],
+) -> String {
+    let verifier_key = NovaCycleFoldVerifierKey::from((verifier_params, z_0.len()));
+    // calldata is the selector and the number of steps followed by z_0, one word per element
+    let z_0_checks = z_0
+        .iter()
+        .enumerate()
+        .map(|(i, z)| {
+            format!(
+                "        require(uint256(bytes32(input[{}:{}])) == {}, \"{}\");\n",
+                4 + 32 * (i + 1),
+                4 + 32 * (i + 2),
+                z,
+                "z_0 is not the initial ivc state"
+            )
+        })
+        .collect::<String>();
+    format!(
+        r#"{}
+
+/**
+ * @notice Verifies decider proofs of grapevine chains starting from the initial ivc state
+ * @dev forwards calls to `verifyNovaProof` of `NovaDecider` after checking z_0
+ */
+contract GrapevineDecider {{
+    NovaDecider public immutable decider;
+
+    constructor() {{
+        decider = new NovaDecider();
+    }}
+
+    fallback(bytes calldata input) external returns (bytes memory) {{
+        require(bytes4(input[:4]) == NovaDecider.verifyNovaProof.selector, "unknown function");
+        require(input.length >= {}, "calldata is too short");
+{}        (bool success, bytes memory output) = address(decider).staticcall(input);
+        require(success, "decider call failed");
+        return output;
+    }}
+}}
+"#,
+        get_decider_template_for_cyclefold_decider(verifier_key),
+        4 + 32 * (z_0.len() + 1),
+        z_0_checks
+    )
+}
+
+/**
+ * Encodes a call to `verifyNovaProof` on the solidity verifier
+ *
+ * @param handoff - the handoff the decider proof was generated for
+ * @param proof - the decider proof
+ * @return - the abi encoded calldata, including the function selector
+ */
+pub fn encode_calldata<FC: FCircuit<Fr>>(
+    handoff: &Handoff,
+    proof: DeciderProof<FC>,
+) -> Result<Vec<u8>, GrapevineError> {
+    // the verifier takes the number of steps followed by z_0 and z_i
+    let selector = get_function_selector_for_nova_cyclefold_verifier(handoff.z_0.len() * 2 + 1);
+    Ok(prepare_calldata(
+        selector,
+        handoff.i,
+        handoff.z_0.clone(),
+        handoff.z_i.clone(),
+        &handoff.big_u_i.clone().into(),
+        &handoff.u_i.clone().into(),
+        proof,
+    )?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backend::fold_degrees;
+    use crate::circom::grapevine_circuit;
+    use crate::params::nova_setup;
+    use crate::utils::fixtures::alice_degree_1;
+    use ark_std::rand::rngs::OsRng;
+    use solidity_verifiers::evm::{compile_solidity, Evm};
+    use sonobe::frontend::circom::CircomFCircuit;
+    use std::path::PathBuf;
+
+    /// Folds degree 1 from z_0 and compresses it, returning the handoff, proof and decider key
+    fn decide_degree_1(
+        z_0: Vec<Fr>,
+    ) -> (
+        Handoff,
+        DeciderProof<CircomFCircuit<Fr>>,
+        DeciderVerifierParams<CircomFCircuit<Fr>>,
+    ) {
+        let f_circuit = grapevine_circuit(
+            PathBuf::from("./circom/artifacts/grapevine.r1cs"),
+            PathBuf::from("./circom/artifacts/grapevine.wasm"),
+        )
+        .unwrap();
+        let mut rng = OsRng;
+        let (prover_params, verifier_params, _) =
+            nova_setup::<_, KZG<'static, Bn254>, _>(f_circuit.clone(), &mut rng).unwrap();
+        let (inputs, path) = alice_degree_1();
+        let nova = fold_degrees::<_, KzgNovaType<CircomFCircuit<Fr>>, _>(
+            &prover_params,
+            f_circuit,
+            z_0,
+            &[inputs],
+        )
+        .unwrap();
+        let handoff = Handoff::from_nova(&nova, path);
+        let (decider_pp, decider_vp) =
+            decider_setup(&mut rng, prover_params, verifier_params, nova.clone()).unwrap();
+        let proof = prove_decider(rng, decider_pp, nova).unwrap();
+        (handoff, proof, decider_vp)
+    }
+
+    #[test]
+    fn test_decider() {
+        let (handoff, proof, decider_vp) = decide_degree_1(get_z0().to_vec());
+        verify_decider::<CircomFCircuit<Fr>>(decider_vp.clone(), &handoff, &proof).unwrap();
+
+        // the proof is not accepted for a chain from another start state
+        let mut forged = handoff.clone();
+        forged.z_0[4] = Fr::from(42);
+        let result = verify_decider::<CircomFCircuit<Fr>>(decider_vp, &forged, &proof);
+        assert!(matches!(result, Err(GrapevineError::Sonobe(msg)) if msg.contains("initial")));
+    }
+
+    #[test]
+    #[ignore = "compiles the verifier with solc, which must be on PATH"]
+    fn test_solidity_decider() {
+        // deploy the verifier to an in-process evm and verify the proof on-chain
+        let (handoff, proof, decider_vp) = decide_degree_1(get_z0().to_vec());
+        let code = solidity_verifier::<CircomFCircuit<Fr>>(decider_vp, &get_z0());
+        let mut evm = Evm::default();
+        let verifier = evm.create(compile_solidity(code, "GrapevineDecider"));
+        let calldata = encode_calldata::<CircomFCircuit<Fr>>(&handoff, proof).unwrap();
+        let (_, output) = evm.call(verifier, calldata.clone());
+        assert_eq!(*output.last().unwrap(), 1);
+
+        // a proof for a different ivc state is rejected
+        let mut tampered = calldata;
+        // byte past the selector and the number of steps: the last byte of z_0[0]
+        tampered[4 + 32 + 31] ^= 1;
+        let (_, output) = evm.call(verifier, tampered);
+        assert_ne!(output.last(), Some(&1));
+
+        // a valid proof of a chain from another start state passes the sonobe contract alone,
+        // but not the wrapper pinning z_0
+        let mut z_0 = get_z0().to_vec();
+        z_0[4] = Fr::from(42);
+        let (handoff, proof, decider_vp) = decide_degree_1(z_0);
+        let code = solidity_verifier::<CircomFCircuit<Fr>>(decider_vp, &get_z0());
+        let calldata = encode_calldata::<CircomFCircuit<Fr>>(&handoff, proof).unwrap();
+        let nova_decider = evm.create(compile_solidity(code.clone(), "NovaDecider"));
+        let (_, output) = evm.call(nova_decider, calldata.clone());
+        assert_eq!(*output.last().unwrap(), 1);
+        let verifier = evm.create(compile_solidity(code, "GrapevineDecider"));
+        let (_, output) = evm.call(verifier, calldata);
+        assert_ne!(output.last(), Some(&1));
+    }
+}
@@ -0,0 +1,249 @@
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use sha2::{Digest, Sha256};
+use std::{fs, path::Path};
+
+use crate::errors::GrapevineError;
+use crate::handoff::Handoff;
+use crate::params::{serialize_params, GrapevineProverParams};
+use crate::utils::{CIRCUIT_VERSION, SECRET_FIELD_LENGTH};
+
+/** Version of the envelope format, bumped whenever the serialization of a handoff changes */
+pub const ENVELOPE_VERSION: u32 = 2;
+
+/**
+ * Fingerprint of the compiled circuit and params a proof is generated with
+ * @dev proofs only verify against the exact circuit and params they were folded with, so both
+ *      are recorded in the envelope to fail early with a readable error
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Fingerprint {
+    // sha256 of the compiled r1cs file
+    pub circuit_digest: [u8; 32],
+    // sha256 of the params as written by `serialize_params`
+    pub params_digest: [u8; 32],
+}
+
+impl Fingerprint {
+    /**
+     * Fingerprints a circuit and its params
+     *
+     * @param r1cs - the bytes of the compiled r1cs file
+     * @param params - the params as written by `serialize_params`
+     * @return - the fingerprint
+     */
+    pub fn new(r1cs: &[u8], params: &[u8]) -> Self {
+        Self {
+            circuit_digest: Sha256::digest(r1cs).into(),
+            params_digest: Sha256::digest(params).into(),
+        }
+    }
+
+    /**
+     * Fingerprints the circuit at a path and loaded prover params
+     *
+     * @param r1cs_path - the path to the compiled r1cs file
+     * @param prover_params - the prover params
+     * @return - the fingerprint
+     */
+    pub fn from_params(
+        r1cs_path: &Path,
+        prover_params: &GrapevineProverParams,
+    ) -> Result<Self, GrapevineError> {
+        Ok(Self::new(
+            &fs::read(r1cs_path)?,
+            &serialize_params(prover_params)?,
+        ))
+    }
+}
+
+/**
+ * A handoff wrapped with the format version, fingerprint, circuit version and phrase width it was
+ * proven with
+ * @dev this is what gets written to disk and sent to the next user or a verifier
+ */
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ProofEnvelope {
+    // always serialized first so that other versions are rejected before parsing the rest
+    pub version: u32,
+    pub fingerprint: Fingerprint,
+    // version of the grapevine statement, proofs of other versions hash differently
+    pub circuit_version: u32,
+    // number of field elements the phrase is packed into
+    pub phrase_width: u32,
+    pub handoff: Handoff,
+}
+
+impl ProofEnvelope {
+    /**
+     * Wraps a handoff proven with the current format and phrase width
+     *
+     * @param handoff - the handoff to wrap
+     * @param fingerprint - the fingerprint of the circuit and params the handoff was proven with
+     * @return - the envelope
+     */
+    pub fn seal(handoff: Handoff, fingerprint: Fingerprint) -> Self {
+        Self {
+            version: ENVELOPE_VERSION,
+            fingerprint,
+            circuit_version: CIRCUIT_VERSION,
+            phrase_width: SECRET_FIELD_LENGTH as u32,
+            handoff,
+        }
+    }
+
+    /**
+     * Unwraps the handoff if it was proven with the expected circuit and params
+     *
+     * @param fingerprint - the fingerprint of the circuit and params to verify or extend with
+     * @return - the handoff
+     */
+    pub fn open(self, fingerprint: &Fingerprint) -> Result<Handoff, GrapevineError> {
+        if self.circuit_version != CIRCUIT_VERSION {
+            return Err(GrapevineError::EnvelopeMismatch(format!(
+                "circuit version {} does not match {}",
+                self.circuit_version, CIRCUIT_VERSION
+            )));
+        }
+        if self.phrase_width != SECRET_FIELD_LENGTH as u32 {
+            return Err(GrapevineError::EnvelopeMismatch(format!(
+                "phrase width {} does not match {}",
+                self.phrase_width, SECRET_FIELD_LENGTH
+            )));
+        }
+        if self.fingerprint.circuit_digest != fingerprint.circuit_digest {
+            return Err(GrapevineError::EnvelopeMismatch(format!(
+                "proven with circuit {} but given circuit {}",
+                hex(&self.fingerprint.circuit_digest),
+                hex(&fingerprint.circuit_digest)
+            )));
+        }
+        if self.fingerprint.params_digest != fingerprint.params_digest {
+            return Err(GrapevineError::EnvelopeMismatch(format!(
+                "proven with params {} but given params {}",
+                hex(&self.fingerprint.params_digest),
+                hex(&fingerprint.params_digest)
+            )));
+        }
+        Ok(self.handoff)
+    }
+
+    /** Serialize the envelope to bytes */
+    pub fn to_bytes(&self) -> Result<Vec<u8>, GrapevineError> {
+        let mut bytes = Vec::new();
+        self.serialize_compressed(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /** Deserialize an envelope from bytes, rejecting other format versions */
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, GrapevineError> {
+        let version = u32::deserialize_compressed(bytes)?;
+        if version != ENVELOPE_VERSION {
+            return Err(GrapevineError::EnvelopeMismatch(format!(
+                "format version {} is not supported, expected {}",
+                version, ENVELOPE_VERSION
+            )));
+        }
+        Ok(Self::deserialize_compressed(bytes)?)
+    }
+}
+
+/** Formats bytes as lowercase hex */
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/** Parses bytes formatted by `hex` */
+pub fn from_hex(hex: &str) -> Result<Vec<u8>, GrapevineError> {
+    let invalid = || GrapevineError::Serialization(String::from("invalid hex"));
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return Err(invalid());
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid()))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::circom::grapevine_circuit;
+    use crate::nova::{prove_first, verify};
+    use crate::params::test_nova_setup;
+    use crate::utils::inputs::random_f_bigint;
+    use ark_bn254::Fr;
+    use sonobe::frontend::circom::CircomFCircuit;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_envelope_mismatches() {
+        let r1cs_path = PathBuf::from("./circom/artifacts/grapevine.r1cs");
+        let f_circuit = grapevine_circuit(
+            r1cs_path.clone(),
+            PathBuf::from("./circom/artifacts/grapevine.wasm"),
+        )
+        .unwrap();
+        let (prover_params, verifier_params) = test_nova_setup(f_circuit.clone());
+        let fingerprint = Fingerprint::from_params(&r1cs_path, &prover_params).unwrap();
+        let handoff = prove_first(
+            &prover_params,
+            f_circuit,
+            String::from("This is a secret"),
+            String::from("alice"),
+            random_f_bigint::<Fr>(),
+        )
+        .unwrap();
+        let bytes = ProofEnvelope::seal(handoff, fingerprint)
+            .to_bytes()
+            .unwrap();
+
+        // the envelope round trips and opens with the same circuit and params
+        let handoff = ProofEnvelope::from_bytes(&bytes)
+            .unwrap()
+            .open(&fingerprint)
+            .unwrap();
+        verify::<CircomFCircuit<Fr>>(&verifier_params, &handoff).unwrap();
+
+        // another circuit or other params are rejected before verifying
+        let other_circuit = Fingerprint {
+            circuit_digest: Sha256::digest(b"another circuit").into(),
+            ..fingerprint
+        };
+        let result = ProofEnvelope::from_bytes(&bytes)
+            .unwrap()
+            .open(&other_circuit);
+        assert!(
+            matches!(result, Err(GrapevineError::EnvelopeMismatch(msg)) if msg.contains("circuit"))
+        );
+        let other_params = Fingerprint {
+            params_digest: Sha256::digest(b"other params").into(),
+            ..fingerprint
+        };
+        let result = ProofEnvelope::from_bytes(&bytes)
+            .unwrap()
+            .open(&other_params);
+        assert!(
+            matches!(result, Err(GrapevineError::EnvelopeMismatch(msg)) if msg.contains("params"))
+        );
+
+        // as are other circuit versions, phrase widths and format versions
+        let mut envelope = ProofEnvelope::from_bytes(&bytes).unwrap();
+        envelope.circuit_version -= 1;
+        let result = envelope.open(&fingerprint);
+        assert!(
+            matches!(result, Err(GrapevineError::EnvelopeMismatch(msg)) if msg.contains("circuit version"))
+        );
+        let mut envelope = ProofEnvelope::from_bytes(&bytes).unwrap();
+        envelope.phrase_width += 1;
+        let result = envelope.open(&fingerprint);
+        assert!(
+            matches!(result, Err(GrapevineError::EnvelopeMismatch(msg)) if msg.contains("phrase width"))
+        );
+        let mut future = bytes.clone();
+        future[..4].copy_from_slice(&(ENVELOPE_VERSION + 1).to_le_bytes());
+        let result = ProofEnvelope::from_bytes(&future);
+        assert!(
+            matches!(result, Err(GrapevineError::EnvelopeMismatch(msg)) if msg.contains("version"))
+        );
+    }
+}
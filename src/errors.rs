@@ -3,14 +3,58 @@ use serde::{Deserialize, Serialize};
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum GrapevineError {
     InputsEmpty,
+    Sonobe(String),
+    Serialization(String),
+    Io(String),
+    Cancelled,
+    InvalidPath(String),
+    InvalidPhraseSet(String),
+    EnvelopeMismatch(String),
+    InvalidParams(String),
+    Overloaded,
+    Timeout,
 }
 
 impl std::fmt::Display for GrapevineError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
-            GrapevineError::InputsEmpty => write!(f, "No private input provided to F circuit!")
+            GrapevineError::InputsEmpty => write!(f, "No private input provided to F circuit!"),
+            GrapevineError::Sonobe(msg) => write!(f, "Sonobe error: {}", msg),
+            GrapevineError::Serialization(msg) => write!(f, "Serialization error: {}", msg),
+            GrapevineError::Io(msg) => write!(f, "IO error: {}", msg),
+            GrapevineError::Cancelled => write!(f, "Proving was cancelled"),
+            GrapevineError::InvalidPath(msg) => write!(f, "Invalid path: {}", msg),
+            GrapevineError::InvalidPhraseSet(msg) => write!(f, "Invalid phrase set: {}", msg),
+            GrapevineError::EnvelopeMismatch(msg) => write!(f, "Envelope mismatch: {}", msg),
+            GrapevineError::InvalidParams(msg) => write!(f, "Invalid params: {}", msg),
+            GrapevineError::Overloaded => write!(f, "Too many proving jobs are queued"),
+            GrapevineError::Timeout => write!(f, "Proving job timed out"),
         }
     }
 }
 
 impl std::error::Error for GrapevineError {}
+
+impl From<sonobe::Error> for GrapevineError {
+    fn from(err: sonobe::Error) -> Self {
+        GrapevineError::Sonobe(err.to_string())
+    }
+}
+
+impl From<ark_serialize::SerializationError> for GrapevineError {
+    fn from(err: ark_serialize::SerializationError) -> Self {
+        GrapevineError::Serialization(err.to_string())
+    }
+}
+
+impl From<Box<dyn std::error::Error>> for GrapevineError {
+    fn from(err: Box<dyn std::error::Error>) -> Self {
+        GrapevineError::Serialization(err.to_string())
+    }
+}
+
+impl From<std::io::Error> for GrapevineError {
+    fn from(err: std::io::Error) -> Self {
+        GrapevineError::Io(err.to_string())
+    }
+}
@@ -0,0 +1,546 @@
+//! C interface to the grapevine prover
+//!
+//! Params and prover sessions are exposed as opaque handles that must be released with their
+//! matching `_free` function. Handoffs and params cross the boundary as byte buffers owned by
+//! rust, which must be released with `grapevine_buffer_free`. Handoff buffers hold a proof
+//! envelope, which only opens with the circuit and params it was proven with. Every fallible
+//! function returns a `GrapevineErrorCode`, with `Ok` (0) signalling success.
+//!
+//! The C declarations live in `include/grapevine.h`, regenerated with `--features header` after
+//! changing this module.
+
+use ark_bn254::Fr;
+use num_bigint::BigInt;
+use sonobe::frontend::circom::CircomFCircuit;
+use std::{
+    ffi::{c_char, CStr},
+    fs,
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::PathBuf,
+    ptr, slice,
+    str::FromStr,
+    sync::Arc,
+};
+
+use crate::circom::grapevine_circuit;
+use crate::envelope::{Fingerprint, ProofEnvelope};
+use crate::errors::GrapevineError;
+use crate::handoff::Handoff;
+use crate::nova::{extend, prove_first, verify};
+use crate::params::{
+    deserialize_params, serialize_params, transparent_nova_setup, GrapevineProverParams,
+    GrapevineVerifierParams, GRAPEVINE_DOMAIN,
+};
+use crate::utils::inputs::{decode_z_i, derive_relationship_secret};
+
+/// Status returned by every fallible function of the C interface
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GrapevineErrorCode {
+    Ok = 0,
+    NullPointer = 1,
+    InvalidArgument = 2,
+    InputsEmpty = 3,
+    Sonobe = 4,
+    Serialization = 5,
+    Io = 6,
+    Panic = 7,
+    Cancelled = 8,
+    EnvelopeMismatch = 9,
+    Overloaded = 10,
+    Timeout = 11,
+}
+
+impl From<&GrapevineError> for GrapevineErrorCode {
+    fn from(err: &GrapevineError) -> Self {
+        match err {
+            GrapevineError::InputsEmpty => GrapevineErrorCode::InputsEmpty,
+            GrapevineError::Sonobe(_) => GrapevineErrorCode::Sonobe,
+            GrapevineError::Serialization(_) => GrapevineErrorCode::Serialization,
+            GrapevineError::Io(_) => GrapevineErrorCode::Io,
+            GrapevineError::Cancelled => GrapevineErrorCode::Cancelled,
+            GrapevineError::InvalidPath(_) => GrapevineErrorCode::InvalidArgument,
+            GrapevineError::InvalidPhraseSet(_) => GrapevineErrorCode::InvalidArgument,
+            GrapevineError::EnvelopeMismatch(_) => GrapevineErrorCode::EnvelopeMismatch,
+            GrapevineError::InvalidParams(_) => GrapevineErrorCode::InvalidArgument,
+            GrapevineError::Overloaded => GrapevineErrorCode::Overloaded,
+            GrapevineError::Timeout => GrapevineErrorCode::Timeout,
+        }
+    }
+}
+
+/// Byte buffer allocated by rust, release with `grapevine_buffer_free`
+#[repr(C)]
+pub struct GrapevineBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl GrapevineBuffer {
+    fn from_vec(bytes: Vec<u8>) -> Self {
+        let mut bytes = bytes.into_boxed_slice();
+        let buffer = Self {
+            data: bytes.as_mut_ptr(),
+            len: bytes.len(),
+        };
+        std::mem::forget(bytes);
+        buffer
+    }
+}
+
+struct ParamsInner {
+    f_circuit: CircomFCircuit<Fr>,
+    prover_params: GrapevineProverParams,
+    verifier_params: GrapevineVerifierParams,
+    fingerprint: Fingerprint,
+}
+
+/// Opaque handle to the grapevine circuit and its prover/ verifier params
+pub struct GrapevineParams {
+    inner: Arc<ParamsInner>,
+}
+
+/// Opaque handle to a proving session holding the current handoff of a chain
+pub struct GrapevineProver {
+    params: Arc<ParamsInner>,
+    handoff: Option<Handoff>,
+}
+
+enum FfiError {
+    NullPointer,
+    InvalidArgument,
+    Grapevine(GrapevineError),
+}
+
+impl From<GrapevineError> for FfiError {
+    fn from(err: GrapevineError) -> Self {
+        FfiError::Grapevine(err)
+    }
+}
+
+/** Runs an ffi function body, mapping errors and panics to error codes */
+fn ffi_call<F: FnOnce() -> Result<(), FfiError>>(f: F) -> GrapevineErrorCode {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => GrapevineErrorCode::Ok,
+        Ok(Err(FfiError::NullPointer)) => GrapevineErrorCode::NullPointer,
+        Ok(Err(FfiError::InvalidArgument)) => GrapevineErrorCode::InvalidArgument,
+        Ok(Err(FfiError::Grapevine(err))) => GrapevineErrorCode::from(&err),
+        Err(_) => GrapevineErrorCode::Panic,
+    }
+}
+
+unsafe fn read_str(s: *const c_char) -> Result<String, FfiError> {
+    if s.is_null() {
+        return Err(FfiError::NullPointer);
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map(String::from)
+        .map_err(|_| FfiError::InvalidArgument)
+}
+
+unsafe fn read_secret(s: *const c_char) -> Result<BigInt, FfiError> {
+    BigInt::from_str(&read_str(s)?).map_err(|_| FfiError::InvalidArgument)
+}
+
+unsafe fn read_bytes<'a>(data: *const u8, len: usize) -> Result<&'a [u8], FfiError> {
+    if data.is_null() {
+        return Err(FfiError::NullPointer);
+    }
+    Ok(slice::from_raw_parts(data, len))
+}
+
+unsafe fn write_handle<T>(out: *mut *mut T, value: T) -> Result<(), FfiError> {
+    if out.is_null() {
+        return Err(FfiError::NullPointer);
+    }
+    *out = Box::into_raw(Box::new(value));
+    Ok(())
+}
+
+unsafe fn write_buffer(out: *mut GrapevineBuffer, bytes: Vec<u8>) -> Result<(), FfiError> {
+    if out.is_null() {
+        return Err(FfiError::NullPointer);
+    }
+    *out = GrapevineBuffer::from_vec(bytes);
+    Ok(())
+}
+
+/// Derives the params published under `GRAPEVINE_DOMAIN` for the circuit at the given paths
+///
+/// # Safety
+/// `r1cs_path` and `wasm_path` must be valid nul-terminated strings and `out` a valid pointer
+#[no_mangle]
+pub unsafe extern "C" fn grapevine_params_setup(
+    r1cs_path: *const c_char,
+    wasm_path: *const c_char,
+    out: *mut *mut GrapevineParams,
+) -> GrapevineErrorCode {
+    ffi_call(|| {
+        let r1cs_path = PathBuf::from(read_str(r1cs_path)?);
+        let f_circuit = grapevine_circuit(r1cs_path.clone(), PathBuf::from(read_str(wasm_path)?))?;
+        let (prover_params, verifier_params) =
+            transparent_nova_setup(f_circuit.clone(), GRAPEVINE_DOMAIN)?;
+        let fingerprint = Fingerprint::from_params(&r1cs_path, &prover_params)?;
+        let inner = ParamsInner {
+            f_circuit,
+            prover_params,
+            verifier_params,
+            fingerprint,
+        };
+        write_handle(
+            out,
+            GrapevineParams {
+                inner: Arc::new(inner),
+            },
+        )
+    })
+}
+
+/// Loads params serialized with `grapevine_params_serialize` for the circuit at the given paths
+///
+/// # Safety
+/// `r1cs_path` and `wasm_path` must be valid nul-terminated strings, `data` must point to `len`
+/// readable bytes and `out` must be a valid pointer
+#[no_mangle]
+pub unsafe extern "C" fn grapevine_params_load(
+    r1cs_path: *const c_char,
+    wasm_path: *const c_char,
+    data: *const u8,
+    len: usize,
+    out: *mut *mut GrapevineParams,
+) -> GrapevineErrorCode {
+    ffi_call(|| {
+        let r1cs_path = PathBuf::from(read_str(r1cs_path)?);
+        let f_circuit = grapevine_circuit(r1cs_path.clone(), PathBuf::from(read_str(wasm_path)?))?;
+        let bytes = read_bytes(data, len)?;
+        let (prover_params, verifier_params) = deserialize_params(bytes, f_circuit.clone())?;
+        let inner = ParamsInner {
+            f_circuit,
+            prover_params,
+            verifier_params,
+            fingerprint: Fingerprint::new(&fs::read(r1cs_path)?, bytes),
+        };
+        write_handle(
+            out,
+            GrapevineParams {
+                inner: Arc::new(inner),
+            },
+        )
+    })
+}
+
+/// Serializes params into a buffer
+///
+/// # Safety
+/// `params` must be a handle returned by this library and `out` a valid pointer
+#[no_mangle]
+pub unsafe extern "C" fn grapevine_params_serialize(
+    params: *const GrapevineParams,
+    out: *mut GrapevineBuffer,
+) -> GrapevineErrorCode {
+    ffi_call(|| {
+        let params = params.as_ref().ok_or(FfiError::NullPointer)?;
+        write_buffer(out, serialize_params(&params.inner.prover_params)?)
+    })
+}
+
+/// Releases params, prover sessions created from them remain valid
+///
+/// # Safety
+/// `params` must be null or a handle returned by this library that was not freed yet
+#[no_mangle]
+pub unsafe extern "C" fn grapevine_params_free(params: *mut GrapevineParams) {
+    if !params.is_null() {
+        drop(Box::from_raw(params));
+    }
+}
+
+/// Starts a proving session, either for a new chain (`handoff` null) or from a received handoff
+///
+/// # Safety
+/// `params` must be a handle returned by this library, `handoff` must be null or point to
+/// `handoff_len` readable bytes and `out` must be a valid pointer
+#[no_mangle]
+pub unsafe extern "C" fn grapevine_prover_new(
+    params: *const GrapevineParams,
+    handoff: *const u8,
+    handoff_len: usize,
+    out: *mut *mut GrapevineProver,
+) -> GrapevineErrorCode {
+    ffi_call(|| {
+        let params = params.as_ref().ok_or(FfiError::NullPointer)?;
+        let handoff = match handoff.is_null() {
+            true => None,
+            false => Some(
+                ProofEnvelope::from_bytes(read_bytes(handoff, handoff_len)?)?
+                    .open(&params.inner.fingerprint)?,
+            ),
+        };
+        write_handle(
+            out,
+            GrapevineProver {
+                params: params.inner.clone(),
+                handoff,
+            },
+        )
+    })
+}
+
+/// Proves degree 1 from knowledge of the phrase
+///
+/// # Safety
+/// `prover` must be a handle returned by this library and all strings valid nul-terminated
+/// strings, `auth_secret` holding a decimal integer
+#[no_mangle]
+pub unsafe extern "C" fn grapevine_prover_prove_first(
+    prover: *mut GrapevineProver,
+    phrase: *const c_char,
+    username: *const c_char,
+    auth_secret: *const c_char,
+) -> GrapevineErrorCode {
+    ffi_call(|| {
+        let prover = prover.as_mut().ok_or(FfiError::NullPointer)?;
+        if prover.handoff.is_some() {
+            return Err(FfiError::InvalidArgument);
+        }
+        let handoff = prove_first(
+            &prover.params.prover_params,
+            prover.params.f_circuit.clone(),
+            read_str(phrase)?,
+            read_str(username)?,
+            read_secret(auth_secret)?,
+        )?;
+        prover.handoff = Some(handoff);
+        Ok(())
+    })
+}
+
+/// Derives the relationship secret to hand to the next user together with the session's handoff
+/// and writes it into a buffer as a decimal string
+///
+/// # Safety
+/// `prover` must be a handle returned by this library, `auth_secret` a valid nul-terminated
+/// string holding a decimal integer and `out` a valid pointer
+#[no_mangle]
+pub unsafe extern "C" fn grapevine_prover_relationship_secret(
+    prover: *const GrapevineProver,
+    auth_secret: *const c_char,
+    out: *mut GrapevineBuffer,
+) -> GrapevineErrorCode {
+    ffi_call(|| {
+        let prover = prover.as_ref().ok_or(FfiError::NullPointer)?;
+        let handoff = prover.handoff.as_ref().ok_or(FfiError::InvalidArgument)?;
+        let output = decode_z_i(&handoff.z_i).ok_or(FfiError::InvalidArgument)?;
+        let secret = derive_relationship_secret(&read_secret(auth_secret)?, output.phrase_hash);
+        write_buffer(out, secret.to_string().into_bytes())
+    })
+}
+
+/// Extends the chain held by the session by one degree of separation
+///
+/// # Safety
+/// `prover` must be a handle returned by this library and all strings valid nul-terminated
+/// strings, secrets holding decimal integers
+#[no_mangle]
+pub unsafe extern "C" fn grapevine_prover_extend(
+    prover: *mut GrapevineProver,
+    prev_username: *const c_char,
+    prev_relationship_secret: *const c_char,
+    username: *const c_char,
+    auth_secret: *const c_char,
+) -> GrapevineErrorCode {
+    ffi_call(|| {
+        let prover = prover.as_mut().ok_or(FfiError::NullPointer)?;
+        let handoff = prover.handoff.clone().ok_or(FfiError::InvalidArgument)?;
+        let handoff = extend(
+            &prover.params.prover_params,
+            prover.params.f_circuit.clone(),
+            handoff,
+            read_str(prev_username)?,
+            read_secret(prev_relationship_secret)?,
+            read_str(username)?,
+            read_secret(auth_secret)?,
+        )?;
+        prover.handoff = Some(handoff);
+        Ok(())
+    })
+}
+
+/// Serializes the current handoff of the session into a buffer, wrapped in a proof envelope
+///
+/// # Safety
+/// `prover` must be a handle returned by this library and `out` a valid pointer
+#[no_mangle]
+pub unsafe extern "C" fn grapevine_prover_handoff(
+    prover: *const GrapevineProver,
+    out: *mut GrapevineBuffer,
+) -> GrapevineErrorCode {
+    ffi_call(|| {
+        let prover = prover.as_ref().ok_or(FfiError::NullPointer)?;
+        let handoff = prover.handoff.clone().ok_or(FfiError::InvalidArgument)?;
+        let envelope = ProofEnvelope::seal(handoff, prover.params.fingerprint);
+        write_buffer(out, envelope.to_bytes()?)
+    })
+}
+
+/// Releases a proving session
+///
+/// # Safety
+/// `prover` must be null or a handle returned by this library that was not freed yet
+#[no_mangle]
+pub unsafe extern "C" fn grapevine_prover_free(prover: *mut GrapevineProver) {
+    if !prover.is_null() {
+        drop(Box::from_raw(prover));
+    }
+}
+
+/// Verifies a serialized handoff, rejecting envelopes proven with another circuit or params
+///
+/// # Safety
+/// `params` must be a handle returned by this library and `data` must point to `len` readable
+/// bytes
+#[no_mangle]
+pub unsafe extern "C" fn grapevine_verify(
+    params: *const GrapevineParams,
+    data: *const u8,
+    len: usize,
+) -> GrapevineErrorCode {
+    ffi_call(|| {
+        let params = params.as_ref().ok_or(FfiError::NullPointer)?;
+        let handoff =
+            ProofEnvelope::from_bytes(read_bytes(data, len)?)?.open(&params.inner.fingerprint)?;
+        verify::<CircomFCircuit<Fr>>(&params.inner.verifier_params, &handoff)?;
+        Ok(())
+    })
+}
+
+/// Releases a buffer returned by this library
+///
+/// # Safety
+/// `buffer` must have been returned by this library and not freed yet
+#[no_mangle]
+pub unsafe extern "C" fn grapevine_buffer_free(buffer: GrapevineBuffer) {
+    if !buffer.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            buffer.data,
+            buffer.len,
+        )));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::inputs::random_f_bigint;
+    use std::ffi::CString;
+
+    fn c_string(s: &str) -> CString {
+        CString::new(s).unwrap()
+    }
+
+    #[test]
+    fn test_ffi_chain() {
+        let r1cs = c_string("./circom/artifacts/grapevine.r1cs");
+        let wasm = c_string("./circom/artifacts/grapevine.wasm");
+        let secrets = (0..2)
+            .map(|_| c_string(&random_f_bigint::<Fr>().to_string()))
+            .collect::<Vec<CString>>();
+        unsafe {
+            // generate params and round trip them through a buffer
+            let mut params: *mut GrapevineParams = ptr::null_mut();
+            let code = grapevine_params_setup(r1cs.as_ptr(), wasm.as_ptr(), &mut params);
+            assert_eq!(code, GrapevineErrorCode::Ok);
+            let mut params_buffer = GrapevineBuffer {
+                data: ptr::null_mut(),
+                len: 0,
+            };
+            let code = grapevine_params_serialize(params, &mut params_buffer);
+            assert_eq!(code, GrapevineErrorCode::Ok);
+            grapevine_params_free(params);
+            let mut params: *mut GrapevineParams = ptr::null_mut();
+            let code = grapevine_params_load(
+                r1cs.as_ptr(),
+                wasm.as_ptr(),
+                params_buffer.data,
+                params_buffer.len,
+                &mut params,
+            );
+            assert_eq!(code, GrapevineErrorCode::Ok);
+            grapevine_buffer_free(params_buffer);
+
+            // prove degree 1
+            let mut prover: *mut GrapevineProver = ptr::null_mut();
+            let code = grapevine_prover_new(params, ptr::null(), 0, &mut prover);
+            assert_eq!(code, GrapevineErrorCode::Ok);
+            let code = grapevine_prover_prove_first(
+                prover,
+                c_string("This is a secret").as_ptr(),
+                c_string("alice").as_ptr(),
+                secrets[0].as_ptr(),
+            );
+            assert_eq!(code, GrapevineErrorCode::Ok);
+            let mut handoff = GrapevineBuffer {
+                data: ptr::null_mut(),
+                len: 0,
+            };
+            let code = grapevine_prover_handoff(prover, &mut handoff);
+            assert_eq!(code, GrapevineErrorCode::Ok);
+            let code = grapevine_verify(params, handoff.data, handoff.len);
+            assert_eq!(code, GrapevineErrorCode::Ok);
+
+            // alice derives the relationship secret she hands to bob
+            let mut relationship_secret = GrapevineBuffer {
+                data: ptr::null_mut(),
+                len: 0,
+            };
+            let code = grapevine_prover_relationship_secret(
+                prover,
+                secrets[0].as_ptr(),
+                &mut relationship_secret,
+            );
+            assert_eq!(code, GrapevineErrorCode::Ok);
+            grapevine_prover_free(prover);
+            let prev_relationship_secret = c_string(
+                std::str::from_utf8(slice::from_raw_parts(
+                    relationship_secret.data,
+                    relationship_secret.len,
+                ))
+                .unwrap(),
+            );
+            grapevine_buffer_free(relationship_secret);
+
+            // extend to degree 2 from the handoff
+            let mut prover: *mut GrapevineProver = ptr::null_mut();
+            let code = grapevine_prover_new(params, handoff.data, handoff.len, &mut prover);
+            assert_eq!(code, GrapevineErrorCode::Ok);
+            grapevine_buffer_free(handoff);
+            let code = grapevine_prover_extend(
+                prover,
+                c_string("alice").as_ptr(),
+                prev_relationship_secret.as_ptr(),
+                c_string("bob").as_ptr(),
+                secrets[1].as_ptr(),
+            );
+            assert_eq!(code, GrapevineErrorCode::Ok);
+            let mut handoff = GrapevineBuffer {
+                data: ptr::null_mut(),
+                len: 0,
+            };
+            let code = grapevine_prover_handoff(prover, &mut handoff);
+            assert_eq!(code, GrapevineErrorCode::Ok);
+            let code = grapevine_verify(params, handoff.data, handoff.len);
+            assert_eq!(code, GrapevineErrorCode::Ok);
+
+            // malformed inputs are reported instead of crashing
+            let code = grapevine_verify(params, handoff.data, handoff.len / 2);
+            assert_eq!(code, GrapevineErrorCode::Serialization);
+            let code = grapevine_verify(params, ptr::null(), 0);
+            assert_eq!(code, GrapevineErrorCode::NullPointer);
+
+            grapevine_buffer_free(handoff);
+            grapevine_prover_free(prover);
+            grapevine_params_free(params);
+        }
+    }
+}
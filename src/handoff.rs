@@ -0,0 +1,182 @@
+use ark_bn254::{Fr, G1Projective as Projective};
+use ark_ec::CurveGroup;
+use ark_grumpkin::Projective as Projective2;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use sonobe::{
+    commitment::CommitmentScheme,
+    folding::nova::{CommittedInstance, Witness},
+    frontend::FCircuit,
+    FoldingScheme,
+};
+
+use crate::errors::GrapevineError;
+use crate::nova::NovaType;
+use crate::params::GrapevineProverParams;
+use crate::utils::path::PathTree;
+
+/** Serializable copy of a sonobe nova witness */
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct WitnessData<C: CurveGroup> {
+    pub e: Vec<C::ScalarField>,
+    pub r_e: C::ScalarField,
+    pub w: Vec<C::ScalarField>,
+    pub r_w: C::ScalarField,
+}
+
+/** Serializable copy of a sonobe nova committed instance */
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct InstanceData<C: CurveGroup> {
+    pub cm_e: C,
+    pub u: C::ScalarField,
+    pub cm_w: C,
+    pub x: Vec<C::ScalarField>,
+}
+
+impl<C: CurveGroup> From<&Witness<C>> for WitnessData<C> {
+    fn from(witness: &Witness<C>) -> Self {
+        Self {
+            e: witness.E.clone(),
+            r_e: witness.rE,
+            w: witness.W.clone(),
+            r_w: witness.rW,
+        }
+    }
+}
+
+impl<C: CurveGroup> From<WitnessData<C>> for Witness<C> {
+    fn from(data: WitnessData<C>) -> Self {
+        Witness {
+            E: data.e,
+            rE: data.r_e,
+            W: data.w,
+            rW: data.r_w,
+        }
+    }
+}
+
+impl<C: CurveGroup> From<&CommittedInstance<C>> for InstanceData<C> {
+    fn from(instance: &CommittedInstance<C>) -> Self {
+        Self {
+            cm_e: instance.cmE,
+            u: instance.u,
+            cm_w: instance.cmW,
+            x: instance.x.clone(),
+        }
+    }
+}
+
+impl<C: CurveGroup> From<InstanceData<C>> for CommittedInstance<C> {
+    fn from(data: InstanceData<C>) -> Self {
+        CommittedInstance {
+            cmE: data.cm_e,
+            u: data.u,
+            cmW: data.cm_w,
+            x: data.x,
+        }
+    }
+}
+
+/**
+ * The full folding state after proving a degree of separation
+ * @dev contains everything the next user needs to extend the chain with another degree, as well
+ *      as everything a verifier needs to check the ivc proof
+ */
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Handoff {
+    pub i: Fr,
+    pub z_0: Vec<Fr>,
+    pub z_i: Vec<Fr>,
+    pub w_i: WitnessData<Projective>,
+    pub u_i: InstanceData<Projective>,
+    pub big_w_i: WitnessData<Projective>,
+    pub big_u_i: InstanceData<Projective>,
+    pub cf_w_i: WitnessData<Projective2>,
+    pub cf_u_i: InstanceData<Projective2>,
+    // salted leaves of the path tree, needed by the next user to insert themselves
+    pub path: PathTree,
+}
+
+impl Handoff {
+    /**
+     * Captures the current state of a nova folding scheme, with any commitment scheme on bn254
+     * @dev the path tree is not part of the folding scheme, so it is passed alongside
+     *
+     * @param nova - the folding scheme to export the state of
+     * @param path - the path tree the last proven degree inserted into
+     * @return - the handoff for the current state
+     */
+    pub fn from_nova<FC, CS1>(nova: &NovaType<FC, CS1>, path: PathTree) -> Self
+    where
+        FC: FCircuit<Fr>,
+        CS1: CommitmentScheme<Projective>,
+    {
+        Self {
+            i: nova.i,
+            z_0: nova.z_0.clone(),
+            z_i: nova.z_i.clone(),
+            w_i: WitnessData::from(&nova.w_i),
+            u_i: InstanceData::from(&nova.u_i),
+            big_w_i: WitnessData::from(&nova.W_i),
+            big_u_i: InstanceData::from(&nova.U_i),
+            cf_w_i: WitnessData::from(&nova.cf_W_i),
+            cf_u_i: InstanceData::from(&nova.cf_U_i),
+            path,
+        }
+    }
+
+    /**
+     * Restores a folding scheme from the handoff so that more steps can be proven
+     *
+     * @param prover_params - the prover params the handoff was generated with
+     * @param f_circuit - the circuit the handoff was generated with
+     * @return - the folding scheme at the state captured in the handoff
+     */
+    pub fn into_nova<FC, CS1>(
+        self,
+        prover_params: &GrapevineProverParams<CS1>,
+        f_circuit: FC,
+    ) -> Result<NovaType<FC, CS1>, GrapevineError>
+    where
+        FC: FCircuit<Fr>,
+        CS1: CommitmentScheme<Projective>,
+    {
+        let mut nova = NovaType::<FC, CS1>::init(prover_params, f_circuit, self.z_0.clone())?;
+        self.load_into(&mut nova);
+        Ok(nova)
+    }
+
+    /**
+     * Overwrites the state of an existing folding scheme with the handoff
+     * @dev avoids recomputing the augmented r1cs when a folding scheme for the same params and
+     *      circuit is already available
+     *
+     * @param nova - the folding scheme to load the state into
+     */
+    pub fn load_into<FC, CS1>(self, nova: &mut NovaType<FC, CS1>)
+    where
+        FC: FCircuit<Fr>,
+        CS1: CommitmentScheme<Projective>,
+    {
+        nova.i = self.i;
+        nova.z_0 = self.z_0;
+        nova.z_i = self.z_i;
+        nova.w_i = self.w_i.into();
+        nova.u_i = self.u_i.into();
+        nova.W_i = self.big_w_i.into();
+        nova.U_i = self.big_u_i.into();
+        nova.cf_W_i = self.cf_w_i.into();
+        nova.cf_U_i = self.cf_u_i.into();
+    }
+
+    /** Serialize the handoff to bytes */
+    pub fn to_bytes(&self) -> Result<Vec<u8>, GrapevineError> {
+        let mut bytes = Vec::new();
+        self.serialize_compressed(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /** Deserialize a handoff from bytes */
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, GrapevineError> {
+        Ok(Self::deserialize_compressed(bytes)?)
+    }
+}
@@ -1,5 +1,20 @@
+#[cfg(feature = "async")]
+pub mod async_prover;
+pub mod backend;
+pub mod batch;
 pub mod circom;
+#[cfg(feature = "decider")]
+pub mod decider;
+pub mod envelope;
+pub mod ffi;
+pub mod handoff;
 pub mod nova;
 pub mod params;
+pub mod progress;
+pub mod ptau;
+pub mod report;
+#[cfg(feature = "service")]
+pub mod service;
 pub mod utils;
+pub mod witness;
 pub mod errors;
@@ -1,46 +1,433 @@
 use ark_bn254::{constraints::GVar, Fr, G1Projective as Projective};
-use ark_crypto_primitives::{
-    crh::{
-        poseidon::constraints::{CRHGadget, CRHParametersVar},
-        poseidon::CRH,
-        CRHScheme, CRHSchemeGadget,
-    },
-    sponge::{poseidon::PoseidonConfig, Absorb},
-};
-use ark_ff::PrimeField;
-use ark_grumpkin::{constraints::GVar as Gvar2, Projective as Projective2};
-use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar, fields::FieldVar};
-use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
-use ark_std::Zero;
-use core::marker::PhantomData;
+#[cfg(feature = "tracing")]
+use ark_ff::{BigInteger, PrimeField};
+use ark_grumpkin::{constraints::GVar as GVar2, Projective as Projective2};
+use num_bigint::BigInt;
 use sonobe::{
-    commitment::{pedersen::Pedersen, CommitmentScheme},
-    folding::nova::{get_r1cs, ProverParams, VerifierParams},
-    frontend::{circom::CircomFCircuit, FCircuit},
-    transcript::poseidon::poseidon_test_config,
+    commitment::pedersen::Pedersen, folding::nova::Nova, frontend::FCircuit, FoldingScheme,
+};
+
+use crate::errors::GrapevineError;
+use crate::handoff::Handoff;
+use crate::params::{GrapevineProverParams, GrapevineVerifierParams};
+use crate::progress::{prove_degree_with_control, ProgressFCircuit, ProveControl};
+use crate::utils::eddsa::{PrivateKey, PublicKey, Signature};
+use crate::utils::inputs::{
+    check_canonical_inputs, get_anonymous_z0, get_z0, hash_phrase, AnonymousPrivateInput,
+    CircomPrivateInput, EddsaPrivateInput, StepInputs,
 };
-use std::time::Instant;
-
-use crate::params::test_nova_setup;
-
-/* WIP
-#[cfg(test)]
-mod test {
-    use super::*;
-    use std::path::PathBuf;
-    use std::time::Instant;
-    #[test]
-    fn test_generate_params() {
-        let r1cs_path = PathBuf::from("./circom/artifacts/circuit.r1cs");
-        let wasm_path = PathBuf::from("./circom/artifacts/circuit.wasm");
-        let f_circuit = CircomFCircuit::<Fr>::new((r1cs_path, wasm_path, 4, 6 + 4)); // 4=ivc_input.lenght, 6+4=external_inputs
-
-        let pre = Instant::now();
-        let (prover_params, verifier_params) = test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit);
-        // let prover_params_str = serde_json::to_string(&prover_params).unwrap();
-        // let verifier_params_str = serde_json::to_string(&verifier_params).unwrap();
-
-        println!("Time to generate params: {:?}", pre.elapsed());
+use crate::utils::path::PathTree;
+use crate::utils::phrase_set::PhraseSet;
+
+/** Nova over bn254/grumpkin, committing with CS1 on bn254 and pedersen for cyclefold */
+pub type NovaType<FC, CS1 = Pedersen<Projective>> =
+    Nova<Projective, GVar, Projective2, GVar2, FC, CS1, Pedersen<Projective2>>;
+
+/**
+ * Proves a degree of separation: a logic step followed by a chaff step
+ * @dev generic over sonobe's `FoldingScheme`, which the pinned sonobe branch only implements
+ *      for nova
+ *
+ * @param folding - the folding scheme to prove the degree with
+ * @param inputs - the private inputs for the logic step
+ */
+pub fn prove_degree<FC, FS, I>(folding: &mut FS, inputs: &I) -> Result<(), GrapevineError>
+where
+    FC: FCircuit<Fr>,
+    FS: FoldingScheme<Projective, Projective2, FC>,
+    I: StepInputs,
+{
+    if inputs.uninitialized() {
+        return Err(GrapevineError::InputsEmpty);
     }
+    // logic step
+    fold_step(folding, inputs)?;
+    // chaff step
+    fold_step(folding, &I::chaff())?;
+    Ok(())
+}
+
+/** Folds a single step of the grapevine circuit */
+fn fold_step<FC, FS, I>(folding: &mut FS, inputs: &I) -> Result<(), GrapevineError>
+where
+    FC: FCircuit<Fr>,
+    FS: FoldingScheme<Projective, Projective2, FC>,
+    I: StepInputs,
+{
+    // `FoldingScheme` does not expose the step counter, but every degree folds a logic step and a
+    // chaff step, so the step follows from the degree and the chaff flag of z_i
+    #[cfg(feature = "tracing")]
+    let _span = {
+        let z_i = folding.state();
+        let (degree, chaff) = (z_i[0].into_bigint(), z_i[3].into_bigint());
+        tracing::info_span!(
+            "fold",
+            step = (2 * degree.as_ref()[0]).saturating_sub(chaff.as_ref()[0]),
+            is_chaff = inputs.is_chaff()
+        )
+        .entered()
+    };
+    // fail with a readable error rather than an unsatisfied range check
+    let external_inputs = inputs.external_inputs()?;
+    check_canonical_inputs(&external_inputs)?;
+    folding.prove_step(external_inputs)?;
+    Ok(())
+}
+
+/**
+ * Proves the first degree of separation from knowledge of the phrase
+ *
+ * @param prover_params - the params to prove with
+ * @param f_circuit - the grapevine circuit
+ * @param phrase - the secret phrase
+ * @param username - the username of the prover
+ * @param auth_secret - the auth secret of the prover
+ * @return - the handoff containing the degree 1 proof
+ */
+pub fn prove_first<FC: FCircuit<Fr>>(
+    prover_params: &GrapevineProverParams,
+    f_circuit: FC,
+    phrase: String,
+    username: String,
+    auth_secret: BigInt,
+) -> Result<Handoff, GrapevineError> {
+    prove_first_with_control(
+        prover_params,
+        f_circuit,
+        phrase,
+        username,
+        auth_secret,
+        &ProveControl::default(),
+    )
+}
+
+/**
+ * Proves the first degree of separation, reporting progress and honoring cancellation
+ *
+ * @param control - the progress observer and cancellation token to use
+ * @dev see `prove_first` for the remaining params
+ */
+pub fn prove_first_with_control<FC: FCircuit<Fr>>(
+    prover_params: &GrapevineProverParams,
+    f_circuit: FC,
+    phrase: String,
+    username: String,
+    auth_secret: BigInt,
+    control: &ProveControl,
+) -> Result<Handoff, GrapevineError> {
+    let f_circuit = ProgressFCircuit::wrap(f_circuit);
+    let mut nova = NovaType::init(prover_params, f_circuit, get_z0().to_vec())?;
+    let phrase_hash = hash_phrase(&phrase)?;
+    let mut path = PathTree::new();
+    let insertion = path.insert_user(&username, &auth_secret, phrase_hash)?;
+    let inputs = CircomPrivateInput {
+        phrase: Some(phrase),
+        usernames: [None, Some(username)],
+        relationship_secret: None,
+        auth_secret: Some(auth_secret),
+        path: Some(insertion),
+        chaff: false,
+    };
+    prove_degree_with_control(&mut nova, &inputs, control)?;
+    Ok(Handoff::from_nova(&nova, path))
+}
+
+/**
+ * Extends a chain received from a previous user by one degree of separation
+ *
+ * @param prover_params - the params to prove with
+ * @param f_circuit - the grapevine circuit
+ * @param handoff - the state of the chain handed off by the previous user
+ * @param prev_username - the username of the previous user
+ * @param prev_relationship_secret - the relationship secret derived by the previous user for
+ *        this chain (see `derive_relationship_secret`)
+ * @param username - the username of the prover
+ * @param auth_secret - the auth secret of the prover
+ * @return - the handoff containing the extended proof
+ */
+pub fn extend<FC: FCircuit<Fr>>(
+    prover_params: &GrapevineProverParams,
+    f_circuit: FC,
+    handoff: Handoff,
+    prev_username: String,
+    prev_relationship_secret: BigInt,
+    username: String,
+    auth_secret: BigInt,
+) -> Result<Handoff, GrapevineError> {
+    extend_with_control(
+        prover_params,
+        f_circuit,
+        handoff,
+        prev_username,
+        prev_relationship_secret,
+        username,
+        auth_secret,
+        &ProveControl::default(),
+    )
+}
+
+/**
+ * Extends a chain by one degree of separation, reporting progress and honoring cancellation
+ *
+ * @param control - the progress observer and cancellation token to use
+ * @dev see `extend` for the remaining params
+ */
+#[allow(clippy::too_many_arguments)]
+pub fn extend_with_control<FC: FCircuit<Fr>>(
+    prover_params: &GrapevineProverParams,
+    f_circuit: FC,
+    handoff: Handoff,
+    prev_username: String,
+    prev_relationship_secret: BigInt,
+    username: String,
+    auth_secret: BigInt,
+    control: &ProveControl,
+) -> Result<Handoff, GrapevineError> {
+    let (phrase_hash, mut path) = (handoff.z_i[1], handoff.path.clone());
+    let mut nova = handoff.into_nova(prover_params, ProgressFCircuit::wrap(f_circuit))?;
+    let insertion = path.insert_user(&username, &auth_secret, phrase_hash)?;
+    let inputs = CircomPrivateInput {
+        phrase: None,
+        usernames: [Some(prev_username), Some(username)],
+        relationship_secret: Some(prev_relationship_secret),
+        auth_secret: Some(auth_secret),
+        path: Some(insertion),
+        chaff: false,
+    };
+    prove_degree_with_control(&mut nova, &inputs, control)?;
+    Ok(Handoff::from_nova(&nova, path))
+}
+
+/**
+ * Proves the first degree of separation with the eddsa grapevine circuit
+ *
+ * @param prover_params - the params to prove with
+ * @param f_circuit - the eddsa grapevine circuit
+ * @param phrase - the secret phrase
+ * @param username - the username of the prover
+ * @param private_key - the babyjubjub private key of the prover
+ * @return - the handoff containing the degree 1 proof
+ */
+pub fn prove_first_eddsa<FC: FCircuit<Fr>>(
+    prover_params: &GrapevineProverParams,
+    f_circuit: FC,
+    phrase: String,
+    username: String,
+    private_key: &PrivateKey,
+) -> Result<Handoff, GrapevineError> {
+    let mut nova = NovaType::init(prover_params, f_circuit, get_z0().to_vec())?;
+    let phrase_hash = hash_phrase(&phrase)?;
+    let mut path = PathTree::new();
+    let secret = BigInt::from(private_key.scalar().clone());
+    let insertion = path.insert_user(&username, &secret, phrase_hash)?;
+    let inputs = EddsaPrivateInput {
+        phrase: Some(phrase),
+        usernames: [None, Some(username)],
+        prev_public_key: None,
+        link_signature: None,
+        public_key: Some(private_key.public_key()),
+        path: Some(insertion),
+        chaff: false,
+    };
+    prove_degree(&mut nova, &inputs)?;
+    Ok(Handoff::from_nova(&nova, path))
+}
+
+/**
+ * Extends a chain with the eddsa grapevine circuit, using the previous user's signature over
+ * the link to the prover (see `sign_link`)
+ *
+ * @param prover_params - the params to prove with
+ * @param f_circuit - the eddsa grapevine circuit
+ * @param handoff - the state of the chain handed off by the previous user
+ * @param prev_username - the username of the previous user
+ * @param prev_public_key - the public key of the previous user
+ * @param link_signature - the previous user's signature over the link to the prover
+ * @param username - the username of the prover
+ * @param private_key - the babyjubjub private key of the prover
+ * @return - the handoff containing the extended proof
+ */
+#[allow(clippy::too_many_arguments)]
+pub fn extend_eddsa<FC: FCircuit<Fr>>(
+    prover_params: &GrapevineProverParams,
+    f_circuit: FC,
+    handoff: Handoff,
+    prev_username: String,
+    prev_public_key: PublicKey,
+    link_signature: Signature,
+    username: String,
+    private_key: &PrivateKey,
+) -> Result<Handoff, GrapevineError> {
+    let (phrase_hash, mut path) = (handoff.z_i[1], handoff.path.clone());
+    let mut nova = handoff.into_nova(prover_params, f_circuit)?;
+    let secret = BigInt::from(private_key.scalar().clone());
+    let insertion = path.insert_user(&username, &secret, phrase_hash)?;
+    let inputs = EddsaPrivateInput {
+        phrase: None,
+        usernames: [Some(prev_username), Some(username)],
+        prev_public_key: Some(prev_public_key),
+        link_signature: Some(link_signature),
+        public_key: Some(private_key.public_key()),
+        path: Some(insertion),
+        chaff: false,
+    };
+    prove_degree(&mut nova, &inputs)?;
+    Ok(Handoff::from_nova(&nova, path))
+}
+
+/**
+ * Proves the first degree of separation with the anonymous grapevine circuit, revealing only
+ * that the phrase is registered in the phrase set
+ *
+ * @param prover_params - the params to prove with
+ * @param f_circuit - the anonymous grapevine circuit
+ * @param phrase_set - the public set of registered phrases
+ * @param phrase - the secret phrase
+ * @param username - the username of the prover
+ * @param auth_secret - the auth secret of the prover
+ * @return - the handoff containing the degree 1 proof
+ */
+pub fn prove_first_anonymous<FC: FCircuit<Fr>>(
+    prover_params: &GrapevineProverParams,
+    f_circuit: FC,
+    phrase_set: &PhraseSet,
+    phrase: String,
+    username: String,
+    auth_secret: BigInt,
+) -> Result<Handoff, GrapevineError> {
+    let phrase_hash = hash_phrase(&phrase)?;
+    let phrase_witness = phrase_set.witness(phrase_hash).ok_or_else(|| {
+        GrapevineError::InvalidPhraseSet(String::from("phrase is not registered"))
+    })?;
+    let z_0 = get_anonymous_z0(phrase_set.root());
+    let mut nova = NovaType::init(prover_params, f_circuit, z_0.to_vec())?;
+    let mut path = PathTree::new();
+    let insertion = path.insert_user(&username, &auth_secret, phrase_hash)?;
+    let inputs = AnonymousPrivateInput {
+        base: CircomPrivateInput {
+            phrase: Some(phrase),
+            usernames: [None, Some(username)],
+            relationship_secret: None,
+            auth_secret: Some(auth_secret),
+            path: Some(insertion),
+            chaff: false,
+        },
+        phrase_hash: None,
+        phrase_witness: Some(phrase_witness),
+    };
+    prove_degree(&mut nova, &inputs)?;
+    Ok(Handoff::from_nova(&nova, path))
+}
+
+/**
+ * Extends a chain with the anonymous grapevine circuit
+ * @dev the phrase hash is not in the ivc state, so the previous user shares it along with the
+ *      relationship secret
+ *
+ * @param prover_params - the params to prove with
+ * @param f_circuit - the anonymous grapevine circuit
+ * @param handoff - the state of the chain handed off by the previous user
+ * @param phrase_hash - the phrase hash shared by the previous user
+ * @param prev_username - the username of the previous user
+ * @param prev_relationship_secret - the relationship secret derived by the previous user
+ * @param username - the username of the prover
+ * @param auth_secret - the auth secret of the prover
+ * @return - the handoff containing the extended proof
+ */
+#[allow(clippy::too_many_arguments)]
+pub fn extend_anonymous<FC: FCircuit<Fr>>(
+    prover_params: &GrapevineProverParams,
+    f_circuit: FC,
+    handoff: Handoff,
+    phrase_hash: Fr,
+    prev_username: String,
+    prev_relationship_secret: BigInt,
+    username: String,
+    auth_secret: BigInt,
+) -> Result<Handoff, GrapevineError> {
+    let mut path = handoff.path.clone();
+    let mut nova = handoff.into_nova(prover_params, f_circuit)?;
+    let insertion = path.insert_user(&username, &auth_secret, phrase_hash)?;
+    let inputs = AnonymousPrivateInput {
+        base: CircomPrivateInput {
+            phrase: None,
+            usernames: [Some(prev_username), Some(username)],
+            relationship_secret: Some(prev_relationship_secret),
+            auth_secret: Some(auth_secret),
+            path: Some(insertion),
+            chaff: false,
+        },
+        phrase_hash: Some(phrase_hash),
+        phrase_witness: None,
+    };
+    prove_degree(&mut nova, &inputs)?;
+    Ok(Handoff::from_nova(&nova, path))
+}
+
+/**
+ * Verifies a handoff of the anonymous grapevine circuit against a phrase set
+ * @dev the circuit only checks membership against the root in z_0, so the verifier must check
+ *      that z_0 starts from the expected phrase set
+ *
+ * @param verifier_params - the params to verify with
+ * @param handoff - the handoff to verify
+ * @param phrase_set_root - the root of the set of registered phrases
+ */
+pub fn verify_anonymous<FC: FCircuit<Fr>>(
+    verifier_params: &GrapevineVerifierParams,
+    handoff: &Handoff,
+    phrase_set_root: Fr,
+) -> Result<(), GrapevineError> {
+    if handoff.z_0 != get_anonymous_z0(phrase_set_root) {
+        return Err(GrapevineError::InvalidPhraseSet(String::from(
+            "chain does not start from the phrase set",
+        )));
+    }
+    verify_ivc::<FC>(verifier_params, handoff)
+}
+
+/**
+ * Verifies the ivc proof contained in a handoff
+ * @dev the ivc proof only shows that z_i follows from z_0, so z_0 must be the initial state
+ *      (`get_z0`), otherwise a chain could start from a chosen degree, phrase hash or path
+ *
+ * @param verifier_params - the params to verify with
+ * @param handoff - the handoff to verify
+ */
+pub fn verify<FC: FCircuit<Fr>>(
+    verifier_params: &GrapevineVerifierParams,
+    handoff: &Handoff,
+) -> Result<(), GrapevineError> {
+    if handoff.z_0 != get_z0() {
+        return Err(GrapevineError::Sonobe(String::from(
+            "chain does not start from the initial ivc state",
+        )));
+    }
+    verify_ivc::<FC>(verifier_params, handoff)
+}
+
+/** Verifies the ivc proof of a handoff from whatever z_0 it claims */
+fn verify_ivc<FC: FCircuit<Fr>>(
+    verifier_params: &GrapevineVerifierParams,
+    handoff: &Handoff,
+) -> Result<(), GrapevineError> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!(
+        "verify",
+        steps = handoff.i.into_bigint().as_ref()[0],
+        constraints = verifier_params.r1cs.A.n_rows,
+        cf_constraints = verifier_params.cf_r1cs.A.n_rows
+    )
+    .entered();
+    let handoff = handoff.clone();
+    NovaType::<FC>::verify(
+        verifier_params.clone(),
+        handoff.z_0,
+        handoff.z_i,
+        handoff.i,
+        (handoff.big_u_i.into(), handoff.big_w_i.into()),
+        (handoff.u_i.into(), handoff.w_i.into()),
+        (handoff.cf_u_i.into(), handoff.cf_w_i.into()),
+    )?;
+    Ok(())
 }
-*/
@@ -1,37 +1,81 @@
-use ark_bn254::{constraints::GVar, Fr, G1Projective as Projective};
-use ark_grumpkin::{constraints::GVar as GVar2, Projective as Projective2};
+use ark_bn254::{
+    constraints::GVar, g1::Config as Bn254Config, Bn254, Fr, G1Projective as Projective,
+};
+use ark_grumpkin::{constraints::GVar as GVar2, GrumpkinConfig, Projective as Projective2};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::RngCore;
 
 use sonobe::{
-    commitment::{pedersen::Pedersen, CommitmentScheme},
+    commitment::{
+        kzg::KZG,
+        pedersen::{Params as PedersenParams, Pedersen},
+        CommitmentScheme,
+    },
     folding::nova::{get_r1cs, ProverParams, VerifierParams},
     frontend::FCircuit,
     transcript::poseidon::poseidon_test_config,
 };
 
-pub fn test_nova_setup<FC: FCircuit<Fr>>(
+use crate::errors::GrapevineError;
+use crate::utils::generators::pedersen_params_from_domain;
+
+/** Prover params committing with CS1 on bn254, cyclefold always commits with pedersen */
+pub type GrapevineProverParams<CS1 = Pedersen<Projective>> =
+    ProverParams<Projective, Projective2, CS1, Pedersen<Projective2>>;
+/** Prover params committing with kzg on bn254, as needed by the decider */
+pub type GrapevineKzgProverParams = GrapevineProverParams<KZG<'static, Bn254>>;
+pub type GrapevineVerifierParams = VerifierParams<Projective, Projective2>;
+
+/**
+ * Generates params for the grapevine circuit with any commitment scheme on bn254
+ *
+ * @param f_circuit - the circuit to generate params for
+ * @param rng - the randomness of the commitment scheme setups
+ * @return - the prover and verifier params, and the verifier params of the bn254 commitment
+ *           scheme (for kzg, the verifying key needed by the decider)
+ */
+pub fn nova_setup<FC, CS1, R>(
     f_circuit: FC,
-) -> (
-    ProverParams<Projective, Projective2, Pedersen<Projective>, Pedersen<Projective2>>,
-    VerifierParams<Projective, Projective2>,
-) {
-    let mut rng = ark_std::test_rng();
+    rng: &mut R,
+) -> Result<
+    (
+        GrapevineProverParams<CS1>,
+        GrapevineVerifierParams,
+        CS1::VerifierParams,
+    ),
+    GrapevineError,
+>
+where
+    FC: FCircuit<Fr>,
+    CS1: CommitmentScheme<Projective>,
+    R: RngCore,
+{
+    #[cfg(feature = "tracing")]
+    let span = tracing::info_span!(
+        "setup",
+        constraints = tracing::field::Empty,
+        cf_constraints = tracing::field::Empty
+    )
+    .entered();
     let poseidon_config = poseidon_test_config::<Fr>();
 
     // get CM & CF_CM len
     let (r1cs, cf_r1cs) =
-        get_r1cs::<Projective, GVar, Projective2, GVar2, FC>(&poseidon_config, f_circuit).unwrap();
+        get_r1cs::<Projective, GVar, Projective2, GVar2, FC>(&poseidon_config, f_circuit)?;
     let cf_len = r1cs.A.n_rows;
     let cf_cf_len = cf_r1cs.A.n_rows;
+    #[cfg(feature = "tracing")]
+    span.record("constraints", cf_len)
+        .record("cf_constraints", cf_cf_len);
 
-    let (pedersen_params, _) = Pedersen::<Projective>::setup(&mut rng, cf_len).unwrap();
-    let (cf_pedersen_params, _) = Pedersen::<Projective2>::setup(&mut rng, cf_cf_len).unwrap();
+    let (cs_params, cs_verifier_params) = CS1::setup(&mut *rng, cf_len)?;
+    let (cf_pedersen_params, _) = Pedersen::<Projective2>::setup(&mut *rng, cf_cf_len)?;
 
-    let prover_params =
-        ProverParams::<Projective, Projective2, Pedersen<Projective>, Pedersen<Projective2>> {
-            poseidon_config: poseidon_config.clone(),
-            cs_params: pedersen_params,
-            cf_cs_params: cf_pedersen_params,
-        };
+    let prover_params = GrapevineProverParams::<CS1> {
+        poseidon_config: poseidon_config.clone(),
+        cs_params,
+        cf_cs_params: cf_pedersen_params,
+    };
 
     let verifier_params = VerifierParams::<Projective, Projective2> {
         poseidon_config: poseidon_config.clone(),
@@ -39,5 +83,226 @@ pub fn test_nova_setup<FC: FCircuit<Fr>>(
         cf_r1cs,
     };
 
+    Ok((prover_params, verifier_params, cs_verifier_params))
+}
+
+/**
+ * Samples params for the grapevine circuit from the public test rng
+ * @dev anyone can recompute the trapdoors from the seed, use `transparent_nova_setup` instead
+ *
+ * @param f_circuit - the circuit to generate params for
+ * @return - the prover and verifier params
+ */
+#[cfg(test)]
+pub fn test_nova_setup<FC: FCircuit<Fr>>(
+    f_circuit: FC,
+) -> (GrapevineProverParams, GrapevineVerifierParams) {
+    let mut rng = ark_std::test_rng();
+    let (prover_params, verifier_params, _) =
+        nova_setup::<FC, Pedersen<Projective>, _>(f_circuit, &mut rng).unwrap();
     (prover_params, verifier_params)
 }
+
+/** Domain string the published grapevine params are derived from */
+pub const GRAPEVINE_DOMAIN: &str = "grapevine-sonobe/pedersen/v1";
+
+/**
+ * Derives params for the grapevine circuit from a public domain string
+ * @dev the pedersen generators of both curves are hashed to the curve instead of sampled, so any
+ *      party can regenerate the params bit-for-bit, see `check_transparent_params`
+ *
+ * @param f_circuit - the circuit to generate params for
+ * @param domain - the public domain string, e.g. GRAPEVINE_DOMAIN
+ * @return - the prover and verifier params
+ */
+pub fn transparent_nova_setup<FC: FCircuit<Fr>>(
+    f_circuit: FC,
+    domain: &str,
+) -> Result<(GrapevineProverParams, GrapevineVerifierParams), GrapevineError> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!("transparent_setup").entered();
+    let poseidon_config = poseidon_test_config::<Fr>();
+    let (r1cs, cf_r1cs) =
+        get_r1cs::<Projective, GVar, Projective2, GVar2, FC>(&poseidon_config, f_circuit)?;
+
+    let prover_params = GrapevineProverParams {
+        poseidon_config: poseidon_config.clone(),
+        cs_params: pedersen_params_from_domain::<Bn254Config>(domain, "bn254", r1cs.A.n_rows),
+        cf_cs_params: pedersen_params_from_domain::<GrumpkinConfig>(
+            domain,
+            "grumpkin",
+            cf_r1cs.A.n_rows,
+        ),
+    };
+    let verifier_params = GrapevineVerifierParams {
+        poseidon_config,
+        r1cs,
+        cf_r1cs,
+    };
+    Ok((prover_params, verifier_params))
+}
+
+/**
+ * Checks that serialized params are exactly the ones derived from a domain string
+ *
+ * @param bytes - the params as written by `serialize_params`
+ * @param f_circuit - the circuit the params are for
+ * @param domain - the public domain string the params claim to be derived from
+ */
+pub fn check_transparent_params<FC: FCircuit<Fr>>(
+    bytes: &[u8],
+    f_circuit: FC,
+    domain: &str,
+) -> Result<(), GrapevineError> {
+    let (prover_params, _) = transparent_nova_setup(f_circuit, domain)?;
+    if serialize_params(&prover_params)? != bytes {
+        return Err(GrapevineError::InvalidParams(format!(
+            "params are not derived from domain \"{}\"",
+            domain
+        )));
+    }
+    Ok(())
+}
+
+/**
+ * Serializes the prover params to bytes
+ * @dev only the pedersen generators are written, the poseidon config is fixed and the
+ *      verifier params are recomputed from the circuit in `deserialize_params`
+ *
+ * @param prover_params - the params to serialize
+ * @return - the compressed serialization of both pedersen parameter sets
+ */
+pub fn serialize_params(prover_params: &GrapevineProverParams) -> Result<Vec<u8>, GrapevineError> {
+    let mut bytes = Vec::new();
+    prover_params.cs_params.h.serialize_compressed(&mut bytes)?;
+    prover_params
+        .cs_params
+        .generators
+        .serialize_compressed(&mut bytes)?;
+    prover_params
+        .cf_cs_params
+        .h
+        .serialize_compressed(&mut bytes)?;
+    prover_params
+        .cf_cs_params
+        .generators
+        .serialize_compressed(&mut bytes)?;
+    Ok(bytes)
+}
+
+/**
+ * Restores prover and verifier params from bytes written by `serialize_params`
+ *
+ * @param bytes - the serialized pedersen parameters
+ * @param f_circuit - the circuit the params were generated for
+ * @return - the prover and verifier params
+ */
+pub fn deserialize_params<FC: FCircuit<Fr>>(
+    bytes: &[u8],
+    f_circuit: FC,
+) -> Result<(GrapevineProverParams, GrapevineVerifierParams), GrapevineError> {
+    let mut reader = bytes;
+    let cs_params = PedersenParams::<Projective> {
+        h: Projective::deserialize_compressed(&mut reader)?,
+        generators: Vec::deserialize_compressed(&mut reader)?,
+    };
+    let cf_cs_params = PedersenParams::<Projective2> {
+        h: Projective2::deserialize_compressed(&mut reader)?,
+        generators: Vec::deserialize_compressed(&mut reader)?,
+    };
+
+    let poseidon_config = poseidon_test_config::<Fr>();
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!("load_params").entered();
+    let (r1cs, cf_r1cs) =
+        get_r1cs::<Projective, GVar, Projective2, GVar2, FC>(&poseidon_config, f_circuit)?;
+
+    let prover_params = GrapevineProverParams {
+        poseidon_config: poseidon_config.clone(),
+        cs_params,
+        cf_cs_params,
+    };
+    let verifier_params = GrapevineVerifierParams {
+        poseidon_config,
+        r1cs,
+        cf_r1cs,
+    };
+    Ok((prover_params, verifier_params))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backend::{fold_degrees, verify_folding};
+    use crate::circom::grapevine_circuit;
+    use crate::handoff::Handoff;
+    use crate::nova::{prove_first, verify, NovaType};
+    use crate::utils::fixtures::alice_degree_1;
+    use crate::utils::inputs::{get_z0, random_f_bigint};
+    use sonobe::frontend::circom::CircomFCircuit;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_kzg_setup() {
+        let f_circuit = grapevine_circuit(
+            PathBuf::from("./circom/artifacts/grapevine.r1cs"),
+            PathBuf::from("./circom/artifacts/grapevine.wasm"),
+        )
+        .unwrap();
+        let mut rng = ark_std::test_rng();
+        let (prover_params, verifier_params, _) =
+            nova_setup::<_, KZG<'static, Bn254>, _>(f_circuit.clone(), &mut rng).unwrap();
+
+        // fold degree 1 committing with kzg on bn254
+        let (inputs, path) = alice_degree_1();
+        let z_0 = get_z0().to_vec();
+        let nova = fold_degrees::<_, NovaType<CircomFCircuit<Fr>, KZG<'static, Bn254>>, _>(
+            &prover_params,
+            f_circuit,
+            z_0.clone(),
+            &[inputs],
+        )
+        .unwrap();
+        verify_folding(verifier_params.clone(), &nova, z_0, 2).unwrap();
+
+        // handoffs do not depend on the commitment scheme
+        let handoff = Handoff::from_nova(&nova, path);
+        verify::<CircomFCircuit<Fr>>(&verifier_params, &handoff).unwrap();
+    }
+
+    #[test]
+    fn test_transparent_setup() {
+        let f_circuit = grapevine_circuit(
+            PathBuf::from("./circom/artifacts/grapevine.r1cs"),
+            PathBuf::from("./circom/artifacts/grapevine.wasm"),
+        )
+        .unwrap();
+        let (prover_params, verifier_params) =
+            transparent_nova_setup(f_circuit.clone(), GRAPEVINE_DOMAIN).unwrap();
+        let bytes = serialize_params(&prover_params).unwrap();
+
+        // anyone can regenerate the params from the domain
+        check_transparent_params(&bytes, f_circuit.clone(), GRAPEVINE_DOMAIN).unwrap();
+        let result = check_transparent_params(&bytes, f_circuit.clone(), "another domain");
+        assert!(matches!(result, Err(GrapevineError::InvalidParams(_))));
+        // while sampled params can not be passed off as derived from it
+        let (sampled_params, _) = test_nova_setup(f_circuit.clone());
+        let result = check_transparent_params(
+            &serialize_params(&sampled_params).unwrap(),
+            f_circuit.clone(),
+            GRAPEVINE_DOMAIN,
+        );
+        assert!(matches!(result, Err(GrapevineError::InvalidParams(_))));
+
+        // derived params prove and verify like sampled ones
+        let handoff = prove_first(
+            &prover_params,
+            f_circuit,
+            String::from("This is a secret"),
+            String::from("alice"),
+            random_f_bigint::<Fr>(),
+        )
+        .unwrap();
+        verify::<CircomFCircuit<Fr>>(&verifier_params, &handoff).unwrap();
+    }
+}
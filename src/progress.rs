@@ -0,0 +1,285 @@
+use ark_bn254::Fr;
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+use sonobe::{frontend::FCircuit, Error};
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use crate::errors::GrapevineError;
+use crate::handoff::Handoff;
+use crate::nova::{prove_degree, NovaType};
+use crate::utils::inputs::StepInputs;
+use crate::utils::path::PathTree;
+
+/**
+ * Phases of a single folding step, reported in order
+ * @dev sonobe folds the running instance and the cyclefold instances in one call without a
+ *      hook in between, so `Cyclefold` is reported once the cyclefold instances are folded,
+ *      right before `Commit`. the first step has no cyclefold instances and skips it
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProvePhase {
+    // computing the witness of the grapevine circuit
+    Witness,
+    // folding the running instance
+    Fold,
+    // folding the cyclefold instances of the non-native point operations
+    Cyclefold,
+    // synthesizing the augmented circuit and committing to its witness
+    Commit,
+}
+
+/** Receives the phases of every folding step while proving */
+pub trait ProgressObserver: Send + Sync {
+    fn on_phase(&self, step: usize, phase: ProvePhase);
+}
+
+impl<F: Fn(usize, ProvePhase) + Send + Sync> ProgressObserver for F {
+    fn on_phase(&self, step: usize, phase: ProvePhase) {
+        self(step, phase)
+    }
+}
+
+/** Shared flag used to abort proving from another thread */
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/** Optional progress observer and cancellation token for a proving call */
+#[derive(Clone, Default)]
+pub struct ProveControl {
+    pub observer: Option<Arc<dyn ProgressObserver>>,
+    pub cancel: Option<CancellationToken>,
+}
+
+impl ProveControl {
+    fn report(&self, step: usize, phase: ProvePhase) {
+        if let Some(observer) = &self.observer {
+            observer.on_phase(step, phase);
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|token| token.is_cancelled())
+    }
+}
+
+/**
+ * Wraps an F circuit to report folding phases and abort on cancellation
+ * @dev sonobe calls `step_native` when computing the witness and `generate_step_constraints`
+ *      after folding the running and cyclefold instances, which are the points where phases are
+ *      reported and cancellation checked
+ */
+#[derive(Clone)]
+pub struct ProgressFCircuit<FC: FCircuit<Fr>> {
+    pub inner: FC,
+    pub control: ProveControl,
+}
+
+impl<FC: FCircuit<Fr>> fmt::Debug for ProgressFCircuit<FC> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ProgressFCircuit")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<FC: FCircuit<Fr>> ProgressFCircuit<FC> {
+    pub fn wrap(inner: FC) -> Self {
+        Self {
+            inner,
+            control: ProveControl::default(),
+        }
+    }
+}
+
+impl<FC: FCircuit<Fr>> FCircuit<Fr> for ProgressFCircuit<FC> {
+    type Params = FC::Params;
+
+    fn new(params: Self::Params) -> Result<Self, Error> {
+        Ok(Self::wrap(FC::new(params)?))
+    }
+
+    fn state_len(&self) -> usize {
+        self.inner.state_len()
+    }
+
+    fn external_inputs_len(&self) -> usize {
+        self.inner.external_inputs_len()
+    }
+
+    fn step_native(
+        &self,
+        i: usize,
+        z_i: Vec<Fr>,
+        external_inputs: Vec<Fr>,
+    ) -> Result<Vec<Fr>, Error> {
+        if self.control.is_cancelled() {
+            return Err(Error::WitnessCalculationError);
+        }
+        self.control.report(i, ProvePhase::Witness);
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!("witness", step = i).entered();
+        let z_i1 = self.inner.step_native(i, z_i, external_inputs)?;
+        #[cfg(feature = "tracing")]
+        drop(span);
+        if self.control.is_cancelled() {
+            return Err(Error::WitnessCalculationError);
+        }
+        self.control.report(i, ProvePhase::Fold);
+        Ok(z_i1)
+    }
+
+    fn generate_step_constraints(
+        &self,
+        cs: ConstraintSystemRef<Fr>,
+        i: usize,
+        z_i: Vec<FpVar<Fr>>,
+        external_inputs: Vec<FpVar<Fr>>,
+    ) -> Result<Vec<FpVar<Fr>>, SynthesisError> {
+        if self.control.is_cancelled() {
+            return Err(SynthesisError::AssignmentMissing);
+        }
+        if i > 0 {
+            self.control.report(i, ProvePhase::Cyclefold);
+        }
+        self.control.report(i, ProvePhase::Commit);
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!(
+            "step_constraints",
+            step = i,
+            constraints_before = cs.num_constraints()
+        )
+        .entered();
+        self.inner
+            .generate_step_constraints(cs, i, z_i, external_inputs)
+    }
+}
+
+/**
+ * Proves a degree of separation while reporting progress and honoring cancellation
+ * @dev the phases of both steps are reported, see `ProvePhase`. if proving is cancelled the
+ *      folding scheme is restored to its state before the degree, so it never ends between the
+ *      logic and chaff steps
+ *
+ * @param nova - the folding scheme to prove the degree with
+ * @param inputs - the private inputs for the logic step
+ * @param control - the progress observer and cancellation token to use
+ */
+pub fn prove_degree_with_control<FC: FCircuit<Fr>, I: StepInputs>(
+    nova: &mut NovaType<ProgressFCircuit<FC>>,
+    inputs: &I,
+    control: &ProveControl,
+) -> Result<(), GrapevineError> {
+    if control.is_cancelled() {
+        return Err(GrapevineError::Cancelled);
+    }
+    // the path tree is untouched by folding, so only the folding state is restored
+    let snapshot = Handoff::from_nova(nova, PathTree::new());
+    nova.F.control = control.clone();
+    let result = prove_degree(nova, inputs);
+    nova.F.control = ProveControl::default();
+    match result {
+        Err(_) if control.is_cancelled() => {
+            snapshot.load_into(nova);
+            Err(GrapevineError::Cancelled)
+        }
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::circom::grapevine_circuit;
+    use crate::nova::{extend_with_control, prove_first_with_control};
+    use crate::params::test_nova_setup;
+    use crate::utils::inputs::{derive_relationship_secret, random_f_bigint};
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_progress_and_cancellation() {
+        let f_circuit = grapevine_circuit(
+            PathBuf::from("./circom/artifacts/grapevine.r1cs"),
+            PathBuf::from("./circom/artifacts/grapevine.wasm"),
+        )
+        .unwrap();
+        let (prover_params, _) = test_nova_setup(f_circuit.clone());
+        let auth_secrets = [random_f_bigint::<Fr>(), random_f_bigint::<Fr>()];
+
+        // record the phases of the logic and chaff steps of degree 1
+        let phases = Arc::new(Mutex::new(Vec::new()));
+        let recorded = phases.clone();
+        let control = ProveControl {
+            observer: Some(Arc::new(move |step: usize, phase: ProvePhase| {
+                recorded.lock().unwrap().push((step, phase))
+            })),
+            cancel: None,
+        };
+        let handoff = prove_first_with_control(
+            &prover_params,
+            f_circuit.clone(),
+            String::from("This is a secret"),
+            String::from("alice"),
+            auth_secrets[0].clone(),
+            &control,
+        )
+        .unwrap();
+        // the first step has no cyclefold instances to fold yet
+        let expected = [
+            (0, ProvePhase::Witness),
+            (0, ProvePhase::Fold),
+            (0, ProvePhase::Commit),
+            (1, ProvePhase::Witness),
+            (1, ProvePhase::Fold),
+            (1, ProvePhase::Cyclefold),
+            (1, ProvePhase::Commit),
+        ];
+        assert_eq!(*phases.lock().unwrap(), expected);
+
+        // cancel degree 2 as soon as folding starts
+        let relationship_secret = derive_relationship_secret(&auth_secrets[0], handoff.z_i[1]);
+        let token = CancellationToken::new();
+        let observed_token = token.clone();
+        let control = ProveControl {
+            observer: Some(Arc::new(move |_: usize, phase: ProvePhase| {
+                if phase == ProvePhase::Fold {
+                    observed_token.cancel();
+                }
+            })),
+            cancel: Some(token),
+        };
+        let result = extend_with_control(
+            &prover_params,
+            f_circuit,
+            handoff,
+            String::from("alice"),
+            relationship_secret,
+            String::from("bob"),
+            auth_secrets[1].clone(),
+            &control,
+        );
+        assert!(matches!(result, Err(GrapevineError::Cancelled)));
+    }
+}
@@ -0,0 +1,401 @@
+//! Loads a KZG SRS over bn254 from a Hermez/snarkjs powers of tau (.ptau) file
+//!
+//! A .ptau file starts with the magic "ptau", a u32 version and a u32 section count, followed by
+//! sections of a u32 type and u64 length. Section 1 holds the header (field element size, base
+//! field modulus and power), section 2 the powers of tau in G1 and section 3 the powers of tau in
+//! G2. Points are uncompressed with coordinates in little-endian montgomery form, which is also
+//! the internal representation of arkworks field elements.
+
+use ark_bn254::{
+    constraints::GVar, Bn254, Fq, Fq2, Fr, G1Affine, G1Projective as Projective, G2Affine,
+};
+use ark_ec::{pairing::Pairing, AffineRepr, VariableBaseMSM};
+use ark_ff::{BigInt, BigInteger, PrimeField, UniformRand, Zero};
+use ark_grumpkin::{constraints::GVar as GVar2, GrumpkinConfig, Projective as Projective2};
+use ark_poly_commit::kzg10::VerifierKey;
+use ark_std::rand::rngs::OsRng;
+use sonobe::{
+    commitment::kzg::ProverKey, folding::nova::get_r1cs, frontend::FCircuit,
+    transcript::poseidon::poseidon_test_config,
+};
+use std::borrow::Cow;
+
+use crate::errors::GrapevineError;
+use crate::params::{GrapevineKzgProverParams, GrapevineVerifierParams};
+use crate::utils::generators::pedersen_params_from_domain;
+
+const SECTION_HEADER: u32 = 1;
+const SECTION_TAU_G1: u32 = 2;
+const SECTION_TAU_G2: u32 = 3;
+// bytes of a bn254 base field element
+const N8: usize = 32;
+
+/** Powers of tau read from a .ptau file */
+#[derive(Clone, Debug)]
+pub struct PowersOfTau {
+    // the ceremony supports circuits of up to 2^power constraints
+    pub power: u32,
+    // [tau^i]_1 for the requested number of powers
+    pub tau_g1: Vec<G1Affine>,
+    // [1]_2 and [tau]_2
+    pub tau_g2: [G2Affine; 2],
+}
+
+/** Cursor over the bytes of a .ptau file */
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], GrapevineError> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| invalid("is truncated"))?;
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> Result<u32, GrapevineError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, GrapevineError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+fn invalid(msg: &str) -> GrapevineError {
+    GrapevineError::InvalidParams(format!("ptau {}", msg))
+}
+
+/** Reads a base field element in little-endian montgomery form */
+fn read_fq(bytes: &[u8]) -> Result<Fq, GrapevineError> {
+    let mut limbs = [0u64; 4];
+    for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks(8)) {
+        *limb = u64::from_le_bytes(chunk.try_into().unwrap());
+    }
+    let limbs = BigInt::new(limbs);
+    if limbs >= Fq::MODULUS {
+        return Err(invalid("contains a field element that is not reduced"));
+    }
+    Ok(Fq::new_unchecked(limbs))
+}
+
+/** Reads an uncompressed G1 point, rejecting points off the curve */
+fn read_g1(bytes: &[u8]) -> Result<G1Affine, GrapevineError> {
+    let (x, y) = (read_fq(&bytes[..N8])?, read_fq(&bytes[N8..])?);
+    // snarkjs encodes the point at infinity as zeroes
+    if x.is_zero() && y.is_zero() {
+        return Ok(G1Affine::zero());
+    }
+    let point = G1Affine::new_unchecked(x, y);
+    if !point.is_on_curve() {
+        return Err(invalid("contains a G1 point that is not on the curve"));
+    }
+    Ok(point)
+}
+
+/** Reads an uncompressed G2 point, rejecting points off the curve or outside the subgroup */
+fn read_g2(bytes: &[u8]) -> Result<G2Affine, GrapevineError> {
+    let x = Fq2::new(read_fq(&bytes[..N8])?, read_fq(&bytes[N8..2 * N8])?);
+    let y = Fq2::new(read_fq(&bytes[2 * N8..3 * N8])?, read_fq(&bytes[3 * N8..])?);
+    if x.is_zero() && y.is_zero() {
+        return Ok(G2Affine::zero());
+    }
+    let point = G2Affine::new_unchecked(x, y);
+    if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(invalid("contains a G2 point that is not in the subgroup"));
+    }
+    Ok(point)
+}
+
+/**
+ * Parses the powers of tau needed for a KZG SRS from a .ptau file
+ * @dev the G1 and G2 powers are checked to come from the same tau with a pairing
+ *
+ * @param bytes - the contents of the .ptau file
+ * @param num_powers - the number of G1 powers to read, starting at tau^0
+ * @return - the powers of tau
+ */
+pub fn read_ptau(bytes: &[u8], num_powers: usize) -> Result<PowersOfTau, GrapevineError> {
+    let mut reader = Reader { bytes, position: 0 };
+    if reader.take(4)? != b"ptau" {
+        return Err(invalid("file does not start with the ptau magic"));
+    }
+    let _version = reader.u32()?;
+    let num_sections = reader.u32()?;
+
+    // index the sections, they may appear in any order
+    let mut sections = Vec::new();
+    for _ in 0..num_sections {
+        let section_type = reader.u32()?;
+        let len = usize::try_from(reader.u64()?).map_err(|_| invalid("section is too large"))?;
+        sections.push((section_type, reader.take(len)?));
+    }
+    let section = |section_type: u32| {
+        sections
+            .iter()
+            .find(|(t, _)| *t == section_type)
+            .map(|(_, data)| *data)
+            .ok_or_else(|| invalid(&format!("is missing section {}", section_type)))
+    };
+
+    let mut header = Reader {
+        bytes: section(SECTION_HEADER)?,
+        position: 0,
+    };
+    if header.u32()? as usize != N8 {
+        return Err(invalid("is not over a 32 byte field"));
+    }
+    let modulus = header.take(N8)?;
+    if modulus != Fq::MODULUS.to_bytes_le().as_slice() {
+        return Err(invalid("is not over the bn254 base field"));
+    }
+    let power = header.u32()?;
+
+    // a ceremony of power p contains 2^(p+1) - 1 powers in G1
+    let available = 2usize
+        .checked_shl(power)
+        .and_then(|powers| powers.checked_sub(1))
+        .ok_or_else(|| invalid(&format!("power {} is too large", power)))?;
+    if num_powers > available {
+        return Err(GrapevineError::InvalidParams(format!(
+            "ptau of power {} holds {} powers of tau but {} are needed",
+            power, available, num_powers
+        )));
+    }
+    let tau_g1 = section(SECTION_TAU_G1)?
+        .chunks_exact(2 * N8)
+        .take(num_powers)
+        .map(read_g1)
+        .collect::<Result<Vec<G1Affine>, GrapevineError>>()?;
+    if tau_g1.len() != num_powers {
+        return Err(invalid("G1 section is truncated"));
+    }
+    let tau_g2 = section(SECTION_TAU_G2)?
+        .chunks_exact(4 * N8)
+        .take(2)
+        .map(read_g2)
+        .collect::<Result<Vec<G2Affine>, GrapevineError>>()?;
+    let tau_g2: [G2Affine; 2] = tau_g2
+        .try_into()
+        .map_err(|_| invalid("G2 section is truncated"))?;
+
+    // e([tau]_1, [1]_2) = e([1]_1, [tau]_2), and every G1 power follows from the previous one
+    let g1 = G1Affine::generator();
+    let g2 = G2Affine::generator();
+    if tau_g1.first() != Some(&g1) || tau_g2[0] != g2 {
+        return Err(invalid("does not start with the generators"));
+    }
+    if num_powers > 1 && Bn254::pairing(tau_g1[1], g2) != Bn254::pairing(g1, tau_g2[1]) {
+        return Err(invalid("G1 and G2 powers are not of the same tau"));
+    }
+    if num_powers > 2 {
+        // batched with a random linear combination instead of a pairing per power
+        let scalars = (0..num_powers - 1)
+            .map(|_| Fr::rand(&mut OsRng))
+            .collect::<Vec<Fr>>();
+        let lower = Projective::msm(&tau_g1[..num_powers - 1], &scalars).unwrap();
+        let upper = Projective::msm(&tau_g1[1..], &scalars).unwrap();
+        if Bn254::pairing(upper, g2) != Bn254::pairing(lower, tau_g2[1]) {
+            return Err(invalid("G1 powers are not consecutive powers of tau"));
+        }
+    }
+
+    Ok(PowersOfTau {
+        power,
+        tau_g1,
+        tau_g2,
+    })
+}
+
+/**
+ * Builds KZG prover and verifier keys from a .ptau file
+ * @dev matches `KZG::setup`, which needs one more power than the vector length rounded up to a
+ *      power of two
+ *
+ * @param bytes - the contents of the .ptau file
+ * @param len - the length of the vectors to commit to
+ * @return - the KZG prover and verifier keys
+ */
+pub fn kzg_keys_from_ptau(
+    bytes: &[u8],
+    len: usize,
+) -> Result<(ProverKey<'static, Projective>, VerifierKey<Bn254>), GrapevineError> {
+    let powers = read_ptau(bytes, len.next_power_of_two() + 1)?;
+    let [h, beta_h] = powers.tau_g2;
+    let verifier_key = VerifierKey {
+        g: powers.tau_g1[0],
+        // only used by hiding commitments, which sonobe does not make
+        gamma_g: G1Affine::zero(),
+        h,
+        beta_h,
+        prepared_h: <Bn254 as Pairing>::G2Prepared::from(h),
+        prepared_beta_h: <Bn254 as Pairing>::G2Prepared::from(beta_h),
+    };
+    let prover_key = ProverKey {
+        powers_of_g: Cow::Owned(powers.tau_g1),
+    };
+    Ok((prover_key, verifier_key))
+}
+
+/**
+ * Generates kzg params for the grapevine circuit from a .ptau file
+ * @dev fails with InvalidParams if the ceremony is too small for the circuit, cyclefold keeps
+ *      committing with pedersen generators derived from the domain string
+ *
+ * @param f_circuit - the circuit to generate params for
+ * @param ptau - the contents of the .ptau file
+ * @param domain - the public domain string of the cyclefold pedersen generators
+ * @return - the prover and verifier params, and the KZG verifier key needed by the decider
+ */
+pub fn ptau_nova_setup<FC: FCircuit<Fr>>(
+    f_circuit: FC,
+    ptau: &[u8],
+    domain: &str,
+) -> Result<
+    (
+        GrapevineKzgProverParams,
+        GrapevineVerifierParams,
+        VerifierKey<Bn254>,
+    ),
+    GrapevineError,
+> {
+    let poseidon_config = poseidon_test_config::<Fr>();
+    let (r1cs, cf_r1cs) =
+        get_r1cs::<Projective, GVar, Projective2, GVar2, FC>(&poseidon_config, f_circuit)?;
+    let (cs_params, cs_verifier_params) = kzg_keys_from_ptau(ptau, r1cs.A.n_rows)?;
+
+    let prover_params = GrapevineKzgProverParams {
+        poseidon_config: poseidon_config.clone(),
+        cs_params,
+        cf_cs_params: pedersen_params_from_domain::<GrumpkinConfig>(
+            domain,
+            "grumpkin",
+            cf_r1cs.A.n_rows,
+        ),
+    };
+    let verifier_params = GrapevineVerifierParams {
+        poseidon_config,
+        r1cs,
+        cf_r1cs,
+    };
+    Ok((prover_params, verifier_params, cs_verifier_params))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_ec::CurveGroup;
+    use ark_ff::Field;
+
+    fn write_fq(bytes: &mut Vec<u8>, fq: Fq) {
+        // the internal representation is already in montgomery form
+        bytes.extend_from_slice(&fq.0.to_bytes_le());
+    }
+
+    fn write_section(bytes: &mut Vec<u8>, section_type: u32, data: &[u8]) {
+        bytes.extend_from_slice(&section_type.to_le_bytes());
+        bytes.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(data);
+    }
+
+    /** Writes a .ptau file of the given power the way snarkjs does */
+    fn ptau(power: u32, tau: Fr) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend_from_slice(&(N8 as u32).to_le_bytes());
+        header.extend_from_slice(&Fq::MODULUS.to_bytes_le());
+        header.extend_from_slice(&power.to_le_bytes());
+        header.extend_from_slice(&power.to_le_bytes());
+
+        let mut tau_g1 = Vec::new();
+        for i in 0..(2u64 << power) - 1 {
+            let point = (G1Affine::generator() * tau.pow([i])).into_affine();
+            write_fq(&mut tau_g1, point.x);
+            write_fq(&mut tau_g1, point.y);
+        }
+        let mut tau_g2 = Vec::new();
+        for i in 0..1u64 << power {
+            let point = (G2Affine::generator() * tau.pow([i])).into_affine();
+            for fq in [point.x.c0, point.x.c1, point.y.c0, point.y.c1] {
+                write_fq(&mut tau_g2, fq);
+            }
+        }
+
+        let mut bytes = b"ptau".to_vec();
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&3u32.to_le_bytes());
+        write_section(&mut bytes, SECTION_HEADER, &header);
+        write_section(&mut bytes, SECTION_TAU_G1, &tau_g1);
+        write_section(&mut bytes, SECTION_TAU_G2, &tau_g2);
+        bytes
+    }
+
+    #[test]
+    fn test_read_ptau() {
+        let tau = Fr::from(1234567);
+        let bytes = ptau(3, tau);
+        let powers = read_ptau(&bytes, 15).unwrap();
+        assert_eq!(powers.power, 3);
+        assert_eq!(
+            powers.tau_g1[5],
+            (G1Affine::generator() * tau.pow([5])).into_affine()
+        );
+        assert_eq!(
+            powers.tau_g2[1],
+            (G2Affine::generator() * tau).into_affine()
+        );
+
+        // kzg keys for vectors of length 5 need 8 + 1 powers
+        let (prover_key, verifier_key) = kzg_keys_from_ptau(&bytes, 5).unwrap();
+        assert_eq!(prover_key.powers_of_g.len(), 9);
+        assert_eq!(verifier_key.beta_h, powers.tau_g2[1]);
+
+        // a ceremony that is too small is rejected with the sizes involved
+        let result = kzg_keys_from_ptau(&bytes, 15);
+        assert!(matches!(result, Err(GrapevineError::InvalidParams(msg)) if msg.contains("17")));
+    }
+
+    #[test]
+    fn test_read_ptau_rejects_malformed() {
+        let bytes = ptau(2, Fr::from(7));
+        assert!(read_ptau(b"zkey", 1).is_err());
+        assert!(read_ptau(&bytes[..bytes.len() - 1], 1).is_err());
+
+        // a G1 power that is not the next power of tau
+        let mut tampered = bytes.clone();
+        let point = (G1Affine::generator() * Fr::from(8)).into_affine();
+        let mut encoded = Vec::new();
+        write_fq(&mut encoded, point.x);
+        write_fq(&mut encoded, point.y);
+        // magic, version, section count, header section and the G1 section header
+        let offset = 4 + 4 + 4 + (12 + 4 + N8 + 4 + 4) + 12;
+        tampered[offset + 2 * 2 * N8..offset + 3 * 2 * N8].copy_from_slice(&encoded);
+        let result = read_ptau(&tampered, 4);
+        assert!(
+            matches!(result, Err(GrapevineError::InvalidParams(msg)) if msg.contains("consecutive"))
+        );
+        // powers that are not read are not checked
+        assert!(read_ptau(&tampered, 2).is_ok());
+
+        // a point off the curve
+        let mut tampered = bytes.clone();
+        tampered[offset + 2 * N8] ^= 1;
+        assert!(read_ptau(&tampered, 2).is_err());
+
+        // a power whose number of G1 powers does not fit in a usize
+        let offset = 4 + 4 + 4 + 12 + 4 + N8;
+        for power in [63u32, 64, u32::MAX] {
+            let mut tampered = bytes.clone();
+            tampered[offset..offset + 4].copy_from_slice(&power.to_le_bytes());
+            let result = read_ptau(&tampered, 2);
+            assert!(
+                matches!(result, Err(GrapevineError::InvalidParams(msg)) if msg.contains("too large"))
+            );
+        }
+    }
+}
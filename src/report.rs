@@ -0,0 +1,105 @@
+use ark_bn254::{constraints::GVar, Fr, G1Projective as Projective};
+use ark_grumpkin::{constraints::GVar as GVar2, Projective as Projective2};
+use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar};
+use ark_relations::r1cs::ConstraintSystem;
+use serde::{Deserialize, Serialize};
+use sonobe::{
+    folding::nova::get_r1cs, frontend::FCircuit, transcript::poseidon::poseidon_test_config,
+};
+
+use crate::errors::GrapevineError;
+use crate::utils::inputs::{get_z0, prepare_external_inputs, CircomPrivateInput};
+use crate::utils::path::{PathInsertion, PathTree};
+
+/** Upper bound on the constraints of a single grapevine step, including ~2k for range checks */
+pub const STEP_CONSTRAINT_BUDGET: usize = 9_000;
+/** Upper bound on the constraints of the augmented nova circuit */
+pub const AUGMENTED_CONSTRAINT_BUDGET: usize = 150_000;
+/** Upper bound on the constraints of the cyclefold circuit */
+pub const CYCLEFOLD_CONSTRAINT_BUDGET: usize = 30_000;
+
+/** Sizes of the circuits folded when proving grapevine degrees */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConstraintReport {
+    // constraints of the grapevine F circuit
+    pub step_constraints: usize,
+    // constraints of the augmented nova circuit, including the F circuit
+    pub augmented_constraints: usize,
+    // constraints added by sonobe on top of the F circuit
+    pub nova_overhead: usize,
+    // witness length of the augmented nova circuit
+    pub augmented_witness_len: usize,
+    // constraints of the cyclefold circuit on the secondary curve
+    pub cyclefold_constraints: usize,
+}
+
+/**
+ * Computes the constraint counts of the grapevine F circuit and the circuits sonobe builds
+ * around it
+ *
+ * @param f_circuit - the grapevine circuit
+ * @return - the size of each circuit
+ */
+pub fn constraint_report<FC: FCircuit<Fr>>(
+    f_circuit: FC,
+) -> Result<ConstraintReport, GrapevineError> {
+    // synthesize a single degree 1 logic step on its own
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    let z_0 = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(get_z0().to_vec()))
+        .map_err(|e| GrapevineError::Sonobe(e.to_string()))?;
+    // random inputs except for the current username, which logic steps require, and the path,
+    // whose slot must be empty in the initial tree
+    let inputs = CircomPrivateInput {
+        usernames: [None, Some(String::from("alice"))],
+        path: Some(PathInsertion {
+            salt: Fr::from(0),
+            siblings: PathTree::new().siblings(0),
+        }),
+        ..CircomPrivateInput::empty(true)
+    };
+    let inputs = prepare_external_inputs::<Fr>(&inputs)?;
+    let inputs = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(inputs))
+        .map_err(|e| GrapevineError::Sonobe(e.to_string()))?;
+    let constraints_before = cs.num_constraints();
+    f_circuit
+        .generate_step_constraints(cs.clone(), 0, z_0, inputs)
+        .map_err(|e| GrapevineError::Sonobe(e.to_string()))?;
+    let step_constraints = cs.num_constraints() - constraints_before;
+
+    let poseidon_config = poseidon_test_config::<Fr>();
+    let (r1cs, cf_r1cs) =
+        get_r1cs::<Projective, GVar, Projective2, GVar2, FC>(&poseidon_config, f_circuit)?;
+
+    Ok(ConstraintReport {
+        step_constraints,
+        augmented_constraints: r1cs.A.n_rows,
+        nova_overhead: r1cs.A.n_rows - step_constraints,
+        augmented_witness_len: r1cs.A.n_cols,
+        cyclefold_constraints: cf_r1cs.A.n_rows,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::circom::grapevine_circuit;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_constraint_budgets() {
+        let f_circuit = grapevine_circuit(
+            PathBuf::from("./circom/artifacts/grapevine.r1cs"),
+            PathBuf::from("./circom/artifacts/grapevine.wasm"),
+        )
+        .unwrap();
+        let report = constraint_report(f_circuit).unwrap();
+        assert!(report.step_constraints > 0);
+        assert!(report.step_constraints <= STEP_CONSTRAINT_BUDGET);
+        assert!(report.augmented_constraints <= AUGMENTED_CONSTRAINT_BUDGET);
+        assert!(report.cyclefold_constraints <= CYCLEFOLD_CONSTRAINT_BUDGET);
+        assert_eq!(
+            report.nova_overhead + report.step_constraints,
+            report.augmented_constraints
+        );
+    }
+}
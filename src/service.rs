@@ -0,0 +1,354 @@
+use ark_bn254::Fr;
+use axum::{
+    extract::{DefaultBodyLimit, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use num_bigint::BigInt;
+use serde::{Deserialize, Serialize};
+use sonobe::frontend::FCircuit;
+use std::{str::FromStr, sync::Arc};
+
+use crate::async_prover::AsyncProver;
+use crate::envelope::{from_hex, hex, Fingerprint, ProofEnvelope};
+use crate::errors::GrapevineError;
+use crate::handoff::Handoff;
+use crate::utils::inputs::decode_z_i;
+
+/**
+ * Largest request body the service accepts
+ * @dev handoffs carry the full nova witnesses and are hex encoded, so a degree 2 handoff is
+ *      already well past the 2 MB axum accepts by default
+ */
+pub const MAX_BODY_BYTES: usize = 256 * 1024 * 1024;
+
+/** Prover and fingerprint shared by the handlers */
+pub struct ServiceState<FC: FCircuit<Fr>> {
+    pub prover: AsyncProver<FC>,
+    // envelopes are only accepted and issued for this circuit and params
+    pub fingerprint: Fingerprint,
+}
+
+/** Body of `POST /prove-first` */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProveFirstRequest {
+    pub phrase: String,
+    pub username: String,
+    // decimal integer
+    pub auth_secret: String,
+}
+
+/** Body of `POST /extend` */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExtendRequest {
+    // hex encoded proof envelope
+    pub handoff: String,
+    pub prev_username: String,
+    // decimal integer
+    pub prev_relationship_secret: String,
+    pub username: String,
+    // decimal integer
+    pub auth_secret: String,
+}
+
+/** Body of `POST /verify` */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VerifyRequest {
+    // hex encoded proof envelope
+    pub handoff: String,
+}
+
+/** Decoded ivc state of a handoff, field elements as decimal strings */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputsResponse {
+    pub steps: String,
+    pub degree: u64,
+    pub phrase_hash: String,
+    pub degree_secret_hash: String,
+    pub next_step_chaff: bool,
+    pub path_accumulator: String,
+    pub path_root: String,
+}
+
+/** Response of the proving endpoints */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HandoffResponse {
+    // hex encoded proof envelope
+    pub handoff: String,
+    pub outputs: OutputsResponse,
+}
+
+/** Error body returned with a non-2xx status */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+/** A grapevine error mapped to an http status */
+pub struct ServiceError(GrapevineError);
+
+impl From<GrapevineError> for ServiceError {
+    fn from(err: GrapevineError) -> Self {
+        ServiceError(err)
+    }
+}
+
+impl IntoResponse for ServiceError {
+    fn into_response(self) -> Response {
+        let status = match &self.0 {
+            GrapevineError::InputsEmpty
+            | GrapevineError::Serialization(_)
+            | GrapevineError::InvalidPath(_)
+            | GrapevineError::InvalidPhraseSet(_)
+            | GrapevineError::InvalidParams(_) => StatusCode::BAD_REQUEST,
+            // the proof does not verify or was made for another circuit
+            GrapevineError::Sonobe(_) | GrapevineError::EnvelopeMismatch(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            GrapevineError::Overloaded => StatusCode::SERVICE_UNAVAILABLE,
+            GrapevineError::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            GrapevineError::Io(_) | GrapevineError::Cancelled => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        let body = ErrorResponse {
+            error: self.0.to_string(),
+        };
+        (status, Json(body)).into_response()
+    }
+}
+
+/**
+ * Builds the routes of the prover service
+ * @dev requests carry the auth secrets of the users, so the service is meant to be run by a
+ *      party the users trust, e.g. on localhost next to a weak client
+ *
+ * @param state - the prover and the fingerprint of its circuit and params
+ * @return - the router serving `/health`, `/prove-first`, `/extend` and `/verify`
+ */
+pub fn router<FC>(state: ServiceState<FC>) -> Router
+where
+    FC: FCircuit<Fr> + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/health", get(|| async { "ok" }))
+        .route("/prove-first", post(prove_first::<FC>))
+        .route("/extend", post(extend::<FC>))
+        .route("/verify", post(verify::<FC>))
+        .layer(DefaultBodyLimit::max(MAX_BODY_BYTES))
+        .with_state(Arc::new(state))
+}
+
+fn parse_secret(secret: &str) -> Result<BigInt, GrapevineError> {
+    BigInt::from_str(secret).map_err(|_| {
+        GrapevineError::Serialization(String::from("secrets must be decimal integers"))
+    })
+}
+
+fn open_handoff<FC: FCircuit<Fr>>(
+    state: &ServiceState<FC>,
+    handoff: &str,
+) -> Result<Handoff, GrapevineError> {
+    ProofEnvelope::from_bytes(&from_hex(handoff)?)?.open(&state.fingerprint)
+}
+
+fn outputs(handoff: &Handoff) -> Result<OutputsResponse, GrapevineError> {
+    let output = decode_z_i(&handoff.z_i)
+        .ok_or_else(|| GrapevineError::Serialization(String::from("malformed ivc state")))?;
+    Ok(OutputsResponse {
+        steps: handoff.i.to_string(),
+        degree: output.degree,
+        phrase_hash: output.phrase_hash.to_string(),
+        degree_secret_hash: output.degree_secret_hash.to_string(),
+        next_step_chaff: output.next_step_chaff,
+        path_accumulator: output.path_accumulator.to_string(),
+        path_root: output.path_root.to_string(),
+    })
+}
+
+fn seal<FC: FCircuit<Fr>>(
+    state: &ServiceState<FC>,
+    handoff: Handoff,
+) -> Result<Json<HandoffResponse>, ServiceError> {
+    let outputs = outputs(&handoff)?;
+    let envelope = ProofEnvelope::seal(handoff, state.fingerprint);
+    Ok(Json(HandoffResponse {
+        handoff: hex(&envelope.to_bytes()?),
+        outputs,
+    }))
+}
+
+async fn prove_first<FC>(
+    State(state): State<Arc<ServiceState<FC>>>,
+    Json(request): Json<ProveFirstRequest>,
+) -> Result<Json<HandoffResponse>, ServiceError>
+where
+    FC: FCircuit<Fr> + Send + Sync + 'static,
+{
+    let handoff = state
+        .prover
+        .prove_first(
+            request.phrase,
+            request.username,
+            parse_secret(&request.auth_secret)?,
+        )
+        .await?;
+    seal(&state, handoff)
+}
+
+async fn extend<FC>(
+    State(state): State<Arc<ServiceState<FC>>>,
+    Json(request): Json<ExtendRequest>,
+) -> Result<Json<HandoffResponse>, ServiceError>
+where
+    FC: FCircuit<Fr> + Send + Sync + 'static,
+{
+    let handoff = state
+        .prover
+        .extend(
+            open_handoff(&state, &request.handoff)?,
+            request.prev_username,
+            parse_secret(&request.prev_relationship_secret)?,
+            request.username,
+            parse_secret(&request.auth_secret)?,
+        )
+        .await?;
+    seal(&state, handoff)
+}
+
+async fn verify<FC>(
+    State(state): State<Arc<ServiceState<FC>>>,
+    Json(request): Json<VerifyRequest>,
+) -> Result<Json<OutputsResponse>, ServiceError>
+where
+    FC: FCircuit<Fr> + Send + Sync + 'static,
+{
+    let handoff = open_handoff(&state, &request.handoff)?;
+    let outputs = outputs(&handoff)?;
+    state.prover.verify(handoff).await?;
+    Ok(Json(outputs))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::async_prover::AsyncProverConfig;
+    use crate::circom::grapevine_circuit;
+    use crate::utils::inputs::{derive_relationship_secret, random_f_bigint};
+    use axum::{body::Body, http::Request};
+    use serde::de::DeserializeOwned;
+    use sonobe::frontend::circom::CircomFCircuit;
+    use std::path::PathBuf;
+    use tower::ServiceExt;
+
+    async fn post<T: Serialize, R: DeserializeOwned>(
+        router: &Router,
+        uri: &str,
+        body: &T,
+    ) -> (StatusCode, R) {
+        let request = Request::post(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::to_vec(body).unwrap()))
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_service_chain() {
+        let r1cs = PathBuf::from("./circom/artifacts/grapevine.r1cs");
+        let f_circuit = grapevine_circuit(
+            r1cs.clone(),
+            PathBuf::from("./circom/artifacts/grapevine.wasm"),
+        )
+        .unwrap();
+        let prover =
+            AsyncProver::<CircomFCircuit<Fr>>::setup(f_circuit, AsyncProverConfig::default())
+                .await
+                .unwrap();
+        let fingerprint = Fingerprint::from_params(&r1cs, &prover.context().prover_params).unwrap();
+        let router = router(ServiceState {
+            prover,
+            fingerprint,
+        });
+
+        // alice proves degree 1
+        let auth_secrets = [random_f_bigint::<Fr>(), random_f_bigint::<Fr>()];
+        let (status, first): (_, HandoffResponse) = post(
+            &router,
+            "/prove-first",
+            &ProveFirstRequest {
+                phrase: String::from("This is a secret"),
+                username: String::from("alice"),
+                auth_secret: auth_secrets[0].to_string(),
+            },
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(first.outputs.degree, 1);
+
+        // bob extends to degree 2 with the relationship secret alice derived
+        let phrase_hash = Fr::from_str(&first.outputs.phrase_hash).unwrap();
+        let relationship_secret = derive_relationship_secret(&auth_secrets[0], phrase_hash);
+        let (status, second): (_, HandoffResponse) = post(
+            &router,
+            "/extend",
+            &ExtendRequest {
+                handoff: first.handoff,
+                prev_username: String::from("alice"),
+                prev_relationship_secret: relationship_secret.to_string(),
+                username: String::from("bob"),
+                auth_secret: auth_secrets[1].to_string(),
+            },
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        // real handoffs do not fit in the default body limit of axum
+        let body = serde_json::to_vec(&VerifyRequest {
+            handoff: second.handoff.clone(),
+        })
+        .unwrap();
+        assert!(body.len() > 2 * 1024 * 1024);
+        assert!(body.len() < MAX_BODY_BYTES);
+
+        // the verifier gets back the decoded outputs
+        let (status, outputs): (_, OutputsResponse) = post(
+            &router,
+            "/verify",
+            &VerifyRequest {
+                handoff: second.handoff.clone(),
+            },
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(outputs, second.outputs);
+        assert_eq!(outputs.degree, 2);
+
+        // malformed handoffs and secrets are rejected as bad requests
+        let (status, _): (_, ErrorResponse) = post(
+            &router,
+            "/verify",
+            &VerifyRequest {
+                handoff: String::from("not hex"),
+            },
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _): (_, ErrorResponse) = post(
+            &router,
+            "/prove-first",
+            &ProveFirstRequest {
+                phrase: String::from("This is a secret"),
+                username: String::from("alice"),
+                auth_secret: String::from("not a number"),
+            },
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
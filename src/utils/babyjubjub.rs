@@ -0,0 +1,95 @@
+use ark_bn254::Fr;
+use ark_ff::{Field, MontFp, One, Zero};
+use num_bigint::BigUint;
+
+/** Coefficient a of the twisted edwards form used by circomlib */
+pub const A: Fr = MontFp!("168700");
+/** Coefficient d of the twisted edwards form used by circomlib */
+pub const D: Fr = MontFp!("168696");
+/** Generator of the prime order subgroup (circomlib's Base8) */
+pub const BASE8: Point = Point {
+    x: MontFp!("5299619240641551281634865583518297030282874472190772894086521144482721001553"),
+    y: MontFp!("16950150798460657717958625567821834550301663161624707787222815936182638968203"),
+};
+/** Order of the subgroup generated by BASE8 */
+const SUBGROUP_ORDER: &str =
+    "2736030358979909402780800718157159386076813972158567259200215660948447373041";
+
+/** Returns the order of the subgroup generated by BASE8 */
+pub fn subgroup_order() -> BigUint {
+    BigUint::parse_bytes(SUBGROUP_ORDER.as_bytes(), 10).unwrap()
+}
+
+/** Affine point on babyjubjub, the curve embedded in the bn254 scalar field */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Point {
+    pub x: Fr,
+    pub y: Fr,
+}
+
+impl Point {
+    /** The neutral element (0, 1) */
+    pub fn identity() -> Self {
+        Self {
+            x: Fr::zero(),
+            y: Fr::one(),
+        }
+    }
+
+    /** Checks that the point satisfies a * x^2 + y^2 = 1 + d * x^2 * y^2 */
+    pub fn on_curve(&self) -> bool {
+        let x2 = self.x.square();
+        let y2 = self.y.square();
+        A * x2 + y2 == Fr::one() + D * x2 * y2
+    }
+
+    /**
+     * Adds two points with the complete twisted edwards addition law
+     * @dev both points must be on the curve, otherwise the denominators may vanish
+     *
+     * @param other - the point to add
+     * @return - the sum of both points
+     */
+    pub fn add(&self, other: &Point) -> Self {
+        let x1x2 = self.x * other.x;
+        let y1y2 = self.y * other.y;
+        let dxy = D * x1x2 * y1y2;
+        let x = (self.x * other.y + self.y * other.x) * (Fr::one() + dxy).inverse().unwrap();
+        let y = (y1y2 - A * x1x2) * (Fr::one() - dxy).inverse().unwrap();
+        Self { x, y }
+    }
+
+    /**
+     * Multiplies the point by a scalar with double and add
+     *
+     * @param scalar - the scalar to multiply by
+     * @return - the point added to itself `scalar` times
+     */
+    pub fn mul_scalar(&self, scalar: &BigUint) -> Self {
+        let mut result = Self::identity();
+        for i in (0..scalar.bits()).rev() {
+            result = result.add(&result);
+            if scalar.bit(i) {
+                result = result.add(self);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_base8_generates_subgroup() {
+        assert!(BASE8.on_curve());
+        assert_eq!(BASE8.mul_scalar(&subgroup_order()), Point::identity());
+        // scalar multiplication distributes over addition of scalars
+        let (a, b) = (BigUint::from(12345u32), BigUint::from(67890u32));
+        assert_eq!(
+            BASE8.mul_scalar(&a).add(&BASE8.mul_scalar(&b)),
+            BASE8.mul_scalar(&(a + b))
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use ark_std::rand::rngs::OsRng;
+use num_bigint::{BigUint, RandBigInt};
+use std::error::Error;
+
+use super::babyjubjub::{subgroup_order, Point, BASE8};
+use super::hash::{link_message, poseidon};
+use super::inputs::serialize_username;
+
+/** BabyJubJub public key, as verified by circomlib's EdDSAPoseidonVerifier */
+pub type PublicKey = Point;
+
+/** BabyJubJub private key, a scalar of the subgroup generated by BASE8 */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrivateKey(BigUint);
+
+/** EdDSA signature (R8, S) over a single field element */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+    pub r8: Point,
+    pub s: BigUint,
+}
+
+impl PrivateKey {
+    /** Generates a random private key */
+    pub fn random() -> Self {
+        Self(OsRng.gen_biguint_range(&BigUint::from(1u32), &subgroup_order()))
+    }
+
+    /**
+     * Creates a private key from a scalar
+     *
+     * @param scalar - the secret scalar, reduced modulo the subgroup order
+     * @return - the private key
+     */
+    pub fn from_scalar(scalar: BigUint) -> Self {
+        Self(scalar % subgroup_order())
+    }
+
+    /** Returns the secret scalar of the key */
+    pub fn scalar(&self) -> &BigUint {
+        &self.0
+    }
+
+    /** Derives the public key A = sk * BASE8 */
+    pub fn public_key(&self) -> PublicKey {
+        BASE8.mul_scalar(&self.0)
+    }
+
+    /**
+     * Signs a message compatibly with circomlib's EdDSAPoseidonVerifier
+     * @dev the nonce is derived deterministically as H(sk, message) so that a key never signs two
+     *      messages with the same nonce
+     *
+     * @param message - the field element to sign
+     * @return - the signature over the message
+     */
+    pub fn sign(&self, message: Fr) -> Signature {
+        let order = subgroup_order();
+        let r =
+            BigUint::from(poseidon(&[Fr::from(self.0.clone()), message]).into_bigint()) % &order;
+        let r8 = BASE8.mul_scalar(&r);
+        let public_key = self.public_key();
+        let hm = challenge(&r8, &public_key, message);
+        let s = (r + BigUint::from(8u32) * hm * &self.0) % order;
+        Signature { r8, s }
+    }
+}
+
+impl Signature {
+    /**
+     * Verifies the signature as done by circomlib's EdDSAPoseidonVerifier
+     * @dev checks S * BASE8 = R8 + 8 * H(R8, A, message) * A
+     *
+     * @param public_key - the public key of the signer
+     * @param message - the signed field element
+     * @return - true if the signature is valid
+     */
+    pub fn verify(&self, public_key: &PublicKey, message: Fr) -> bool {
+        if self.s >= subgroup_order() || !self.r8.on_curve() || !public_key.on_curve() {
+            return false;
+        }
+        let hm = challenge(&self.r8, public_key, message);
+        let left = BASE8.mul_scalar(&self.s);
+        let right = self
+            .r8
+            .add(&public_key.mul_scalar(&(BigUint::from(8u32) * hm)));
+        left == right
+    }
+}
+
+/** Computes the challenge H(R8x, R8y, Ax, Ay, message) */
+fn challenge(r8: &Point, public_key: &PublicKey, message: Fr) -> BigUint {
+    BigUint::from(poseidon(&[r8.x, r8.y, public_key.x, public_key.y, message]).into_bigint())
+}
+
+/**
+ * Authorizes the next user to extend a chain from the signer's degree
+ *
+ * @param private_key - the private key of the user handing off the chain
+ * @param next_username - the username of the user receiving the chain
+ * @param next_public_key - the public key the user receiving the chain proves with
+ * @param phrase_hash - the phrase hash of the chain (z_i[1])
+ * @return - the signature over the link, to be sent with the handoff
+ */
+pub fn sign_link(
+    private_key: &PrivateKey,
+    next_username: &String,
+    next_public_key: &PublicKey,
+    phrase_hash: Fr,
+) -> Result<Signature, Box<dyn Error>> {
+    let username = Fr::from(serialize_username(next_username)?.to_biguint().unwrap());
+    Ok(private_key.sign(link_message(username, next_public_key, phrase_hash)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::random_fr;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let private_key = PrivateKey::random();
+        let public_key = private_key.public_key();
+        assert!(public_key.on_curve());
+
+        let message = random_fr();
+        let signature = private_key.sign(message);
+        assert!(signature.verify(&public_key, message));
+        // signatures do not verify for other messages or keys
+        assert!(!signature.verify(&public_key, message + Fr::from(1)));
+        assert!(!signature.verify(&PrivateKey::random().public_key(), message));
+    }
+
+    #[test]
+    fn test_sign_link() {
+        let private_key = PrivateKey::random();
+        let next_public_key = PrivateKey::random().public_key();
+        let phrase_hash = random_fr();
+        let signature = sign_link(
+            &private_key,
+            &String::from("bob"),
+            &next_public_key,
+            phrase_hash,
+        )
+        .unwrap();
+        let username = Fr::from(
+            serialize_username(&String::from("bob"))
+                .unwrap()
+                .to_biguint()
+                .unwrap(),
+        );
+        let message = link_message(username, &next_public_key, phrase_hash);
+        assert!(signature.verify(&private_key.public_key(), message));
+        // the link does not extend to another key of the next user
+        let other_key = PrivateKey::random().public_key();
+        let message = link_message(username, &other_key, phrase_hash);
+        assert!(!signature.verify(&private_key.public_key(), message));
+    }
+}
@@ -0,0 +1,19 @@
+use ark_bn254::Fr;
+
+use super::inputs::{random_f_bigint, CircomPrivateInput};
+use super::path::PathTree;
+
+/// Inputs of alice proving degree 1 from the test phrase, with the path tree holding her leaf
+pub fn alice_degree_1() -> (CircomPrivateInput, PathTree) {
+    let mut path = PathTree::new();
+    let insertion = path.insert(&String::from("alice"), Fr::from(1)).unwrap();
+    let inputs = CircomPrivateInput {
+        phrase: Some(String::from("This is a secret")),
+        usernames: [None, Some(String::from("alice"))],
+        relationship_secret: None,
+        auth_secret: Some(random_f_bigint::<Fr>()),
+        path: Some(insertion),
+        chaff: false,
+    };
+    (inputs, path)
+}
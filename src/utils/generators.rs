@@ -0,0 +1,109 @@
+use ark_ec::{
+    short_weierstrass::{Affine, Projective, SWCurveConfig},
+    AffineRepr, CurveGroup,
+};
+use ark_ff::PrimeField;
+use sha2::{Digest, Sha256};
+use sonobe::commitment::pedersen::Params as PedersenParams;
+
+/**
+ * Hashes a label to a curve point by try-and-increment
+ * @dev the x coordinate is sampled from 512 bits of sha256 output to keep the bias negligible,
+ *      the point with the smaller y coordinate is taken and the cofactor cleared. no one knows the
+ *      discrete log of the result relative to any other point derived this way
+ *
+ * @param domain - the public domain string of the setup
+ * @param label - what the point is used for, separating points derived from the same domain
+ * @param index - the index of the point under the label
+ * @return - the point
+ */
+pub fn hash_to_curve<P>(domain: &str, label: &str, index: u64) -> Affine<P>
+where
+    P: SWCurveConfig,
+    P::BaseField: PrimeField,
+{
+    let mut counter: u64 = 0;
+    loop {
+        let mut bytes = Vec::with_capacity(64);
+        for half in 0u8..2 {
+            let mut hasher = Sha256::new();
+            hasher.update((domain.len() as u64).to_le_bytes());
+            hasher.update(domain.as_bytes());
+            hasher.update((label.len() as u64).to_le_bytes());
+            hasher.update(label.as_bytes());
+            hasher.update(index.to_le_bytes());
+            hasher.update(counter.to_le_bytes());
+            hasher.update([half]);
+            bytes.extend_from_slice(&hasher.finalize());
+        }
+        let x = P::BaseField::from_le_bytes_mod_order(&bytes);
+        if let Some(point) = Affine::<P>::get_point_from_x_unchecked(x, false) {
+            let point = point.clear_cofactor();
+            if !point.is_zero() {
+                return point;
+            }
+        }
+        counter += 1;
+    }
+}
+
+/**
+ * Derives pedersen params from a public domain string instead of a random setup
+ * @dev matches the shape of `Pedersen::setup`, which rounds the number of generators up to a
+ *      power of two
+ *
+ * @param domain - the public domain string of the setup
+ * @param curve - the name of the curve, separating the params of both curves of the cycle
+ * @param len - the length of the vectors the params commit to
+ * @return - the pedersen params
+ */
+pub fn pedersen_params_from_domain<P>(
+    domain: &str,
+    curve: &str,
+    len: usize,
+) -> PedersenParams<Projective<P>>
+where
+    P: SWCurveConfig,
+    P::BaseField: PrimeField,
+{
+    let generators_label = format!("{}/generators", curve);
+    let generators = (0..len.next_power_of_two() as u64)
+        .map(|i| hash_to_curve::<P>(domain, &generators_label, i))
+        .collect();
+    let h = hash_to_curve::<P>(domain, &format!("{}/h", curve), 0).into_group();
+    PedersenParams { h, generators }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bn254::g1::Config as Bn254Config;
+    use ark_grumpkin::GrumpkinConfig;
+
+    #[test]
+    fn test_hash_to_curve() {
+        let point = hash_to_curve::<Bn254Config>("grapevine", "test", 0);
+        assert!(point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve());
+        let point = hash_to_curve::<GrumpkinConfig>("grapevine", "test", 0);
+        assert!(point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve());
+
+        // the same inputs always give the same point, any other input another point
+        let point = hash_to_curve::<Bn254Config>("grapevine", "test", 0);
+        assert_eq!(point, hash_to_curve::<Bn254Config>("grapevine", "test", 0));
+        assert_ne!(point, hash_to_curve::<Bn254Config>("grapevine", "test", 1));
+        assert_ne!(point, hash_to_curve::<Bn254Config>("grapevine", "other", 0));
+        assert_ne!(point, hash_to_curve::<Bn254Config>("other", "test", 0));
+        // lengths are hashed so that moving bytes between the domain and label changes the point
+        assert_ne!(point, hash_to_curve::<Bn254Config>("grapevinet", "est", 0));
+    }
+
+    #[test]
+    fn test_pedersen_params_from_domain() {
+        let params = pedersen_params_from_domain::<Bn254Config>("grapevine", "bn254", 5);
+        assert_eq!(params.generators.len(), 8);
+        let again = pedersen_params_from_domain::<Bn254Config>("grapevine", "bn254", 5);
+        assert_eq!(params.generators, again.generators);
+        assert_eq!(params.h, again.h);
+        assert!(!params.generators.contains(&params.h.into_affine()));
+    }
+}
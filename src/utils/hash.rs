@@ -0,0 +1,205 @@
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use light_poseidon::{Poseidon, PoseidonHasher};
+
+use super::babyjubjub::Point;
+use super::SECRET_FIELD_LENGTH;
+
+/**
+ * Computes the circomlib compatible poseidon hash of the inputs
+ *
+ * @param inputs - between 1 and 12 field elements to hash
+ * @return - the hash, matching circomlib's Poseidon(inputs.len())
+ */
+pub fn poseidon(inputs: &[Fr]) -> Fr {
+    Poseidon::<Fr>::new_circom(inputs.len())
+        .unwrap()
+        .hash(inputs)
+        .unwrap()
+}
+
+// domain tags of the grapevine hashes, must match circom/templates/domains.circom
+pub const PHRASE_DOMAIN: &str = "grapevine/v2/phrase";
+pub const DEGREE_SECRET_DOMAIN: &str = "grapevine/v2/degree_secret";
+pub const PUBKEY_DEGREE_SECRET_DOMAIN: &str = "grapevine/v2/pk_degree_secret";
+pub const RELATIONSHIP_DOMAIN: &str = "grapevine/v2/relationship";
+pub const LINK_DOMAIN: &str = "grapevine/v2/link";
+pub const PATH_ACCUMULATOR_DOMAIN: &str = "grapevine/v2/path_accumulator";
+pub const PATH_LEAF_DOMAIN: &str = "grapevine/v2/path_leaf";
+// only hashed natively, the circuit never sees the preimage of a path salt
+pub const PATH_SALT_DOMAIN: &str = "grapevine/v2/path_salt";
+
+/**
+ * Converts a domain tag to the field element prepended to the hashed inputs
+ *
+ * @param domain - the ascii domain tag, at most 31 bytes
+ * @return - the tag read as a big endian integer
+ */
+pub fn domain_tag(domain: &str) -> Fr {
+    Fr::from_be_bytes_mod_order(domain.as_bytes())
+}
+
+/**
+ * Computes the poseidon hash of the inputs under a domain tag
+ *
+ * @param domain - the domain tag of the hash
+ * @param inputs - between 1 and 11 field elements to hash
+ * @return - the hash, matching circomlib's Poseidon(inputs.len() + 1) over the tag and inputs
+ */
+pub fn tagged_poseidon(domain: &str, inputs: &[Fr]) -> Fr {
+    let mut tagged = Vec::with_capacity(inputs.len() + 1);
+    tagged.push(domain_tag(domain));
+    tagged.extend_from_slice(inputs);
+    poseidon(&tagged)
+}
+
+/**
+ * Computes the hash of a serialized phrase as done at degree 0 of the grapevine circuit
+ *
+ * @param phrase - the phrase serialized into field elements
+ * @return - the phrase hash
+ */
+pub fn phrase_hash(phrase: &[Fr; SECRET_FIELD_LENGTH]) -> Fr {
+    tagged_poseidon(PHRASE_DOMAIN, phrase)
+}
+
+/**
+ * Computes the relationship secret a user shares with the next degree for a phrase
+ *
+ * @param auth_secret - the auth secret of the user
+ * @param phrase_hash - the hash of the phrase
+ * @return - the relationship secret
+ */
+pub fn relationship_secret(auth_secret: Fr, phrase_hash: Fr) -> Fr {
+    tagged_poseidon(RELATIONSHIP_DOMAIN, &[auth_secret, phrase_hash])
+}
+
+/**
+ * Computes the degree secret hash binding a user to the phrase
+ *
+ * @param phrase_hash - the hash of the phrase
+ * @param username - the serialized username of the user
+ * @param relationship_secret - the relationship secret of the user for the phrase
+ * @return - the degree secret hash
+ */
+pub fn degree_secret_hash(phrase_hash: Fr, username: Fr, relationship_secret: Fr) -> Fr {
+    tagged_poseidon(
+        DEGREE_SECRET_DOMAIN,
+        &[phrase_hash, username, relationship_secret],
+    )
+}
+
+/**
+ * Computes the degree secret hash binding a user and their public key to the phrase
+ * @dev used by the eddsa circuit in place of `degree_secret_hash`
+ *
+ * @param phrase_hash - the hash of the phrase
+ * @param username - the serialized username of the user
+ * @param public_key - the babyjubjub public key of the user
+ * @return - the degree secret hash
+ */
+pub fn pubkey_degree_secret_hash(phrase_hash: Fr, username: Fr, public_key: &Point) -> Fr {
+    tagged_poseidon(
+        PUBKEY_DEGREE_SECRET_DOMAIN,
+        &[phrase_hash, username, public_key.x, public_key.y],
+    )
+}
+
+/**
+ * Computes the message the previous user signs to let the next user extend a chain
+ * @dev covers the public key of the next user, so the link can not be used with another key
+ *
+ * @param next_username - the serialized username of the next user
+ * @param next_public_key - the babyjubjub public key of the next user
+ * @param phrase_hash - the hash of the phrase
+ * @return - the link message
+ */
+pub fn link_message(next_username: Fr, next_public_key: &Point, phrase_hash: Fr) -> Fr {
+    tagged_poseidon(
+        LINK_DOMAIN,
+        &[
+            next_username,
+            next_public_key.x,
+            next_public_key.y,
+            phrase_hash,
+        ],
+    )
+}
+
+/**
+ * Extends the running hash of the users on a path
+ * @dev accumulates the salted path leaves rather than the usernames, which are easy to guess
+ *
+ * @param path_accumulator - the accumulator before the user, 0 for an empty path
+ * @param leaf - the path leaf of the user, see `path_leaf`
+ * @return - the accumulator including the user
+ */
+pub fn path_accumulator(path_accumulator: Fr, leaf: Fr) -> Fr {
+    tagged_poseidon(PATH_ACCUMULATOR_DOMAIN, &[path_accumulator, leaf])
+}
+
+/**
+ * Computes the leaf committing to a user in the path tree
+ *
+ * @param username - the serialized username of the user
+ * @param salt - the salt hiding the username until disclosed
+ * @return - the leaf
+ */
+pub fn path_leaf(username: Fr, salt: Fr) -> Fr {
+    tagged_poseidon(PATH_LEAF_DOMAIN, &[username, salt])
+}
+
+/**
+ * Derives the salt of a user's path leaf from a secret only they know
+ * @dev binding the salt to the phrase hash keeps leaves of the same user unlinkable across chains
+ *
+ * @param secret - the auth secret (or eddsa private key scalar) of the user
+ * @param phrase_hash - the hash of the phrase
+ * @param username - the serialized username of the user
+ * @return - the salt
+ */
+pub fn path_salt(secret: Fr, phrase_hash: Fr, username: Fr) -> Fr {
+    tagged_poseidon(PATH_SALT_DOMAIN, &[secret, phrase_hash, username])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::CIRCUIT_VERSION;
+    use std::fs;
+
+    #[test]
+    fn test_domain_tags_match_circuit() {
+        let circuit = fs::read_to_string("./circom/templates/domains.circom").unwrap();
+        for (function, domain) in [
+            ("PHRASE_DOMAIN", PHRASE_DOMAIN),
+            ("DEGREE_SECRET_DOMAIN", DEGREE_SECRET_DOMAIN),
+            ("PUBKEY_DEGREE_SECRET_DOMAIN", PUBKEY_DEGREE_SECRET_DOMAIN),
+            ("RELATIONSHIP_DOMAIN", RELATIONSHIP_DOMAIN),
+            ("LINK_DOMAIN", LINK_DOMAIN),
+            ("PATH_ACCUMULATOR_DOMAIN", PATH_ACCUMULATOR_DOMAIN),
+            ("PATH_LEAF_DOMAIN", PATH_LEAF_DOMAIN),
+        ] {
+            // tags fit in a field element and are versioned with the circuit
+            assert!(domain.len() <= 31);
+            assert!(domain.starts_with(&format!("grapevine/v{}/", CIRCUIT_VERSION)));
+            let hex: String = domain.bytes().map(|b| format!("{:02x}", b)).collect();
+            let expected = format!("function {}() {{\n    return 0x{};", function, hex);
+            assert!(circuit.contains(&expected), "{} does not match", function);
+        }
+        assert!(PATH_SALT_DOMAIN.starts_with(&format!("grapevine/v{}/", CIRCUIT_VERSION)));
+    }
+
+    #[test]
+    fn test_tagged_hashes_are_separated() {
+        let inputs = [Fr::from(1), Fr::from(2)];
+        assert_ne!(
+            tagged_poseidon(PATH_LEAF_DOMAIN, &inputs),
+            tagged_poseidon(PATH_ACCUMULATOR_DOMAIN, &inputs)
+        );
+        assert_ne!(
+            tagged_poseidon(PATH_LEAF_DOMAIN, &inputs),
+            poseidon(&inputs)
+        );
+    }
+}
@@ -1,16 +1,30 @@
 use ark_bn254::Fr;
-use ark_ff::{BigInteger, PrimeField};
-use ark_std::rand::rngs::OsRng;
+use ark_ff::{BigInteger, PrimeField, Zero};
+use ark_std::rand::{rngs::OsRng, RngCore};
 use num_bigint::{BigInt, RandBigInt, Sign::Plus};
 use std::error::Error;
 
-use super::{MAX_SECRET_LENGTH, MAX_USERNAME_LENGTH, SECRET_FIELD_LENGTH};
+use crate::errors::GrapevineError;
+
+use super::eddsa::{PrivateKey, PublicKey, Signature};
+use super::hash::{phrase_hash, relationship_secret};
+use super::path::{empty_path_root, PathInsertion};
+use super::phrase_set::PhraseWitness;
+use super::random_fr;
+use super::{
+    CANONICAL_FELT_BITS, IVC_STATE_LENGTH, MAX_SECRET_LENGTH, MAX_USERNAME_LENGTH, PATH_TREE_DEPTH,
+    PHRASE_SET_DEPTH, SECRET_FIELD_LENGTH,
+};
 
 #[derive(Clone, Debug)]
 pub struct CircomPrivateInput {
     pub phrase: Option<String>,
     pub usernames: [Option<String>; 2],
-    pub auth_secrets: [Option<BigInt>; 2],
+    // relationship secret derived by the previous user, None at degree 1
+    pub relationship_secret: Option<BigInt>,
+    pub auth_secret: Option<BigInt>,
+    // insertion of the current user into the path tree of the chain
+    pub path: Option<PathInsertion>,
     pub chaff: bool,
 }
 
@@ -24,7 +38,51 @@ impl CircomPrivateInput {
         Self {
             phrase: None,
             usernames: [None, None],
-            auth_secrets: [None, None],
+            relationship_secret: None,
+            auth_secret: None,
+            path: None,
+            chaff,
+        }
+    }
+
+    pub fn uninitialized(&self) -> bool {
+        let not_chaff = self.phrase.is_none()
+            && self.usernames.iter().all(|u| u.is_none())
+            && self.relationship_secret.is_none()
+            && self.auth_secret.is_none()
+            && self.path.is_none();
+        not_chaff && !self.chaff
+    }
+}
+
+/** Private inputs of a step of the eddsa grapevine circuit */
+#[derive(Clone, Debug)]
+pub struct EddsaPrivateInput {
+    pub phrase: Option<String>,
+    pub usernames: [Option<String>; 2],
+    // public key of the previous user and their signature over the link, None at degree 1
+    pub prev_public_key: Option<PublicKey>,
+    pub link_signature: Option<Signature>,
+    pub public_key: Option<PublicKey>,
+    // insertion of the current user into the path tree of the chain
+    pub path: Option<PathInsertion>,
+    pub chaff: bool,
+}
+
+impl EddsaPrivateInput {
+    /**
+     * Creates empty inputs
+     *
+     * @param chaff - if true, should compute random vars for chaff in circuit
+     */
+    pub fn empty(chaff: bool) -> Self {
+        Self {
+            phrase: None,
+            usernames: [None, None],
+            prev_public_key: None,
+            link_signature: None,
+            public_key: None,
+            path: None,
             chaff,
         }
     }
@@ -32,18 +90,165 @@ impl CircomPrivateInput {
     pub fn uninitialized(&self) -> bool {
         let not_chaff = self.phrase.is_none()
             && self.usernames.iter().all(|u| u.is_none())
-            && self.auth_secrets.iter().all(|a| a.is_none());
+            && self.prev_public_key.is_none()
+            && self.link_signature.is_none()
+            && self.public_key.is_none()
+            && self.path.is_none();
         not_chaff && !self.chaff
     }
 }
 
-/** Get the starting ivc inputs (z0) for the grapevine circuit */
-pub fn get_z0<F: PrimeField>() -> [F; 4] {
-    (0..4)
-        .map(|_| F::zero())
-        .collect::<Vec<F>>()
-        .try_into()
-        .unwrap()
+/** Private inputs of the anonymous grapevine circuit, which hides the phrase hash */
+#[derive(Clone, Debug)]
+pub struct AnonymousPrivateInput {
+    // inputs shared with the grapevine circuit
+    pub base: CircomPrivateInput,
+    // phrase hash shared by the previous user, None at degree 1
+    pub phrase_hash: Option<Fr>,
+    // witness that the phrase is registered in the phrase set, only at degree 1
+    pub phrase_witness: Option<PhraseWitness>,
+}
+
+impl AnonymousPrivateInput {
+    /**
+     * Creates empty inputs, phrase set membership is only checked by degree 1 logic steps
+     *
+     * @param chaff - whether the inputs are for a chaff step
+     * @return - the empty inputs
+     */
+    pub fn empty(chaff: bool) -> Self {
+        Self {
+            base: CircomPrivateInput::empty(chaff),
+            phrase_hash: None,
+            phrase_witness: None,
+        }
+    }
+}
+
+/** Private inputs of a single step of one of the grapevine circuits */
+pub trait StepInputs {
+    /** Creates the inputs of a chaff step */
+    fn chaff() -> Self;
+
+    /** Returns true if no inputs were given for a logic step */
+    fn uninitialized(&self) -> bool;
+
+    /** Returns true if the inputs are for a chaff step */
+    fn is_chaff(&self) -> bool;
+
+    /** Marshals the inputs into the external inputs of the circuit */
+    fn external_inputs(&self) -> Result<Vec<Fr>, GrapevineError>;
+}
+
+impl StepInputs for CircomPrivateInput {
+    fn chaff() -> Self {
+        Self::empty(true)
+    }
+
+    fn uninitialized(&self) -> bool {
+        CircomPrivateInput::uninitialized(self)
+    }
+
+    fn is_chaff(&self) -> bool {
+        self.chaff
+    }
+
+    fn external_inputs(&self) -> Result<Vec<Fr>, GrapevineError> {
+        prepare_external_inputs(self)
+    }
+}
+
+impl StepInputs for EddsaPrivateInput {
+    fn chaff() -> Self {
+        Self::empty(true)
+    }
+
+    fn uninitialized(&self) -> bool {
+        EddsaPrivateInput::uninitialized(self)
+    }
+
+    fn is_chaff(&self) -> bool {
+        self.chaff
+    }
+
+    fn external_inputs(&self) -> Result<Vec<Fr>, GrapevineError> {
+        prepare_eddsa_external_inputs(self)
+    }
+}
+
+impl StepInputs for AnonymousPrivateInput {
+    fn chaff() -> Self {
+        Self::empty(true)
+    }
+
+    fn uninitialized(&self) -> bool {
+        self.base.uninitialized() && self.phrase_hash.is_none() && self.phrase_witness.is_none()
+    }
+
+    fn is_chaff(&self) -> bool {
+        self.base.chaff
+    }
+
+    fn external_inputs(&self) -> Result<Vec<Fr>, GrapevineError> {
+        prepare_anonymous_external_inputs(self)
+    }
+}
+
+/** Decoded ivc state (z_i) of the grapevine circuit */
+#[derive(Clone, Debug, PartialEq)]
+pub struct GrapevineOutput<F: PrimeField> {
+    pub degree: u64,
+    pub phrase_hash: F,
+    pub degree_secret_hash: F,
+    pub next_step_chaff: bool,
+    pub path_accumulator: F,
+    pub path_root: F,
+}
+
+/**
+ * Get the starting ivc inputs (z0) for the grapevine circuit
+ * @dev every value is 0 except for the path root, which starts as the root of the empty tree
+ */
+pub fn get_z0() -> [Fr; IVC_STATE_LENGTH] {
+    let mut z_0 = [Fr::zero(); IVC_STATE_LENGTH];
+    z_0[5] = empty_path_root();
+    z_0
+}
+
+/**
+ * Get the starting ivc inputs (z0) for the anonymous grapevine circuit
+ *
+ * @param phrase_set_root - the root of the set of registered phrases
+ * @return - z0 carrying the phrase set root in place of the phrase hash
+ */
+pub fn get_anonymous_z0(phrase_set_root: Fr) -> [Fr; IVC_STATE_LENGTH] {
+    let mut z_0 = get_z0();
+    z_0[1] = phrase_set_root;
+    z_0
+}
+
+/**
+ * Decodes the ivc state output by the grapevine circuit
+ *
+ * @param z_i - the ivc state after some number of folds
+ * @return - the named values of the state, or None if the state is malformed
+ */
+pub fn decode_z_i<F: PrimeField>(z_i: &[F]) -> Option<GrapevineOutput<F>> {
+    if z_i.len() != IVC_STATE_LENGTH {
+        return None;
+    }
+    let degree = z_i[0].into_bigint();
+    if degree.num_bits() > 64 {
+        return None;
+    }
+    Some(GrapevineOutput {
+        degree: degree.as_ref()[0],
+        phrase_hash: z_i[1],
+        degree_secret_hash: z_i[2],
+        next_step_chaff: z_i[3] == F::one(),
+        path_accumulator: z_i[4],
+        path_root: z_i[5],
+    })
 }
 
 /** Generates a random field element for given field as bigint */
@@ -53,6 +258,45 @@ pub fn random_f_bigint<F: PrimeField>() -> BigInt {
     OsRng.gen_bigint_range(&lower_bound, &upper_bound)
 }
 
+/** Generates a random value that is a canonical phrase chunk or username (< 2^248) */
+pub fn random_canonical_bigint() -> BigInt {
+    let mut bytes = [0u8; CANONICAL_FELT_BITS / 8];
+    OsRng.fill_bytes(&mut bytes);
+    BigInt::from_bytes_be(Plus, &bytes)
+}
+
+/**
+ * Checks that the phrase chunks and usernames of a step are canonical 31 byte encodings, as
+ * range checked by the circuits
+ * @dev the phrase and usernames lead the external inputs of every grapevine circuit
+ *
+ * @param external_inputs - the external inputs of a step
+ * @return - an error naming the first value that does not fit in 248 bits
+ */
+pub fn check_canonical_inputs<F: PrimeField>(external_inputs: &[F]) -> Result<(), GrapevineError> {
+    if external_inputs.len() < SECRET_FIELD_LENGTH + 2 {
+        return Err(GrapevineError::Serialization(String::from(
+            "external inputs are missing the phrase or usernames",
+        )));
+    }
+    for (i, value) in external_inputs[..SECRET_FIELD_LENGTH + 2]
+        .iter()
+        .enumerate()
+    {
+        if value.into_bigint().num_bits() as usize > CANONICAL_FELT_BITS {
+            let name = match i < SECRET_FIELD_LENGTH {
+                true => format!("phrase chunk {}", i),
+                false => format!("username {}", i - SECRET_FIELD_LENGTH),
+            };
+            return Err(GrapevineError::Serialization(format!(
+                "{} does not fit in {} bits",
+                name, CANONICAL_FELT_BITS
+            )));
+        }
+    }
+    Ok(())
+}
+
 /**
  * Converts a given word to array of 6 field elements
  * @dev split into 31-byte strings to fit in finite field and pad with 0's where necessary
@@ -85,6 +329,19 @@ pub fn serialize_phrase(phrase: &String) -> Result<[BigInt; SECRET_FIELD_LENGTH]
     Ok(chunks)
 }
 
+/**
+ * Computes the phrase hash a chain commits to from the phrase
+ *
+ * @param phrase - the secret phrase
+ * @return - the phrase hash output by degree 1 (z_i[1])
+ */
+pub fn hash_phrase(phrase: &String) -> Result<Fr, Box<dyn Error>> {
+    let phrase = serialize_phrase(phrase)?;
+    Ok(phrase_hash(
+        &to_field_elements::<Fr>(&phrase).try_into().unwrap(),
+    ))
+}
+
 /**
 * Converts a given username to a field element
 *
@@ -103,47 +360,200 @@ pub fn serialize_username(username: &String) -> Result<BigInt, Box<dyn Error>> {
     Ok(BigInt::from_bytes_be(Plus, &bytes))
 }
 
-pub fn prepare_external_inputs<F: PrimeField>(inputs: &CircomPrivateInput) -> Vec<F> {
-    // handle phrase presence (if not present infer chaff)
-    let phrase = match &inputs.phrase {
-        Some(phrase) => serialize_phrase(&phrase).unwrap().to_vec(),
-        None => (0..6)
-            .map(|_| random_f_bigint::<F>())
-            .collect::<Vec<BigInt>>(),
-    };
+/**
+ * Derives the secret a user hands to the next degree in place of their auth secret
+ * @dev the secret is bound to the phrase hash, so revealing it does not let anyone extend
+ *      other chains that the user is part of. it is not bound to the next username though: the
+ *      user's degree commits to it before the next user is known, so every user the chain is
+ *      handed to receives the same secret and can extend it. links authorized per recipient
+ *      need the eddsa circuit, where the previous user signs the next username and key
+ *
+ * @param auth_secret - the auth secret of the user
+ * @param phrase_hash - the phrase hash of the chain being extended (z_i[1])
+ * @return - the relationship secret for the chain
+ */
+pub fn derive_relationship_secret(auth_secret: &BigInt, phrase_hash: Fr) -> BigInt {
+    let (_, bytes) = auth_secret.to_bytes_le();
+    let secret = relationship_secret(Fr::from_le_bytes_mod_order(&bytes), phrase_hash);
+    BigInt::from_bytes_le(Plus, &secret.into_bigint().to_bytes_le())
+}
 
-    // determine inputs: first step ([0] = None), Nth step ([1] = Some), and chaff ([2] = None)
-    // marshal usernames
-    let usernames = match inputs.usernames[0] {
-        Some(_) => inputs
-            .usernames
-            .iter()
-            .map(|u| serialize_username(&u.clone().unwrap()).unwrap())
-            .collect::<Vec<BigInt>>(),
-        None => match &inputs.usernames[1] {
-            Some(username) => vec![BigInt::from(0), serialize_username(&username).unwrap()],
-            None => vec![random_f_bigint::<F>(), random_f_bigint::<F>()],
-        },
-    };
+/**
+ * Marshals the private inputs of the grapevine circuit into its external inputs
+ *
+ * @param inputs - the private inputs of the step
+ * @return - the external inputs, or an error if the phrase or a username can not be serialized
+ */
+pub fn prepare_external_inputs<F: PrimeField>(
+    inputs: &CircomPrivateInput,
+) -> Result<Vec<F>, GrapevineError> {
+    let phrase = phrase_inputs(&inputs.phrase)?;
+    let usernames = username_inputs(&inputs.usernames)?;
 
-    // marshal auth secrets
-    let auth_sec = match inputs.auth_secrets[0] {
-        Some(_) => inputs
-            .auth_secrets
-            .iter()
-            .map(|a| a.clone().unwrap())
-            .collect::<Vec<BigInt>>(),
-        None => match &inputs.auth_secrets[1] {
-            Some(auth_secret) => vec![BigInt::from(0), auth_secret.clone()],
-            None => vec![random_f_bigint::<F>(), random_f_bigint::<F>()],
-        },
+    // marshal the previous user's relationship secret and the current auth secret
+    let auth_sec = match &inputs.auth_secret {
+        Some(auth_secret) => vec![
+            inputs
+                .relationship_secret
+                .clone()
+                .unwrap_or(BigInt::from(0)),
+            auth_secret.clone(),
+        ],
+        None => vec![random_f_bigint::<F>(), random_f_bigint::<F>()],
     };
 
     // NOTE: probably wold be better that the inputs are prepared already as F instead of
     // BigInt (at the methods serialize_phrase, serialize_username).
 
-    let inp: Vec<BigInt> = [phrase, usernames, auth_sec].concat();
-    inp.iter()
+    let path = path_inputs(&inputs.path);
+
+    let inp: Vec<BigInt> = [phrase, usernames, auth_sec, path].concat();
+    Ok(to_field_elements(&inp))
+}
+
+/**
+ * Marshals the private inputs of the eddsa grapevine circuit into its external inputs
+ * @dev degree 1 and chaff steps carry a signature by a random key over a random message, so
+ *      that the signature verifier (disabled for those steps) still receives well formed points
+ *
+ * @param inputs - the private inputs of the step
+ * @return - the external inputs of the eddsa circuit
+ */
+pub fn prepare_eddsa_external_inputs(
+    inputs: &EddsaPrivateInput,
+) -> Result<Vec<Fr>, GrapevineError> {
+    let phrase = to_field_elements::<Fr>(&phrase_inputs(&inputs.phrase)?);
+    let usernames = to_field_elements::<Fr>(&username_inputs(&inputs.usernames)?);
+
+    // marshal the previous user's public key and signature over the link
+    let (prev_public_key, signature) = match (&inputs.prev_public_key, &inputs.link_signature) {
+        (Some(public_key), Some(signature)) => (*public_key, signature.clone()),
+        _ => {
+            let key = PrivateKey::random();
+            (key.public_key(), key.sign(random_fr()))
+        }
+    };
+    let public_key = inputs
+        .public_key
+        .unwrap_or_else(|| PrivateKey::random().public_key());
+
+    Ok([
+        phrase,
+        usernames,
+        vec![
+            prev_public_key.x,
+            prev_public_key.y,
+            signature.r8.x,
+            signature.r8.y,
+            Fr::from(signature.s),
+            public_key.x,
+            public_key.y,
+        ],
+        to_field_elements::<Fr>(&path_inputs(&inputs.path)),
+    ]
+    .concat())
+}
+
+/**
+ * Marshals the private inputs of the anonymous grapevine circuit into its external inputs
+ * @dev steps without a phrase witness carry index 0 with random siblings, as the index is
+ *      range checked even when membership is not
+ *
+ * @param inputs - the private inputs of the step
+ * @return - the external inputs of the anonymous circuit
+ */
+pub fn prepare_anonymous_external_inputs(
+    inputs: &AnonymousPrivateInput,
+) -> Result<Vec<Fr>, GrapevineError> {
+    let phrase_hash = inputs.phrase_hash.unwrap_or_else(random_fr);
+    let phrase_witness = match &inputs.phrase_witness {
+        Some(witness) => [
+            vec![Fr::from(witness.index as u64)],
+            witness.siblings.to_vec(),
+        ]
+        .concat(),
+        None => [
+            vec![Fr::zero()],
+            (0..PHRASE_SET_DEPTH).map(|_| random_fr()).collect(),
+        ]
+        .concat(),
+    };
+    Ok([
+        prepare_external_inputs::<Fr>(&inputs.base)?,
+        vec![phrase_hash],
+        phrase_witness,
+    ]
+    .concat())
+}
+
+/**
+ * Serializes the phrase, or random values if no phrase is given (chaff / degree N)
+ * @dev random values stay canonical as the circuit range checks the phrase on every step
+ */
+fn phrase_inputs(phrase: &Option<String>) -> Result<Vec<BigInt>, GrapevineError> {
+    match phrase {
+        Some(phrase) => Ok(serialize_phrase(phrase)
+            .map_err(|e| GrapevineError::Serialization(e.to_string()))?
+            .to_vec()),
+        None => Ok((0..SECRET_FIELD_LENGTH)
+            .map(|_| random_canonical_bigint())
+            .collect::<Vec<BigInt>>()),
+    }
+}
+
+/**
+ * Serializes the usernames of a first step, Nth step or chaff step (random values)
+ * @dev a first step has previous username 0, which the circuit only accepts at degree 0
+ */
+fn username_inputs(usernames: &[Option<String>; 2]) -> Result<Vec<BigInt>, GrapevineError> {
+    // determine inputs: first step ([0] = None), Nth step ([1] = Some), and chaff ([2] = None)
+    match usernames {
+        [Some(prev_username), Some(username)] => Ok(vec![
+            username_input(prev_username)?,
+            username_input(username)?,
+        ]),
+        [None, Some(username)] => Ok(vec![BigInt::from(0), username_input(username)?]),
+        [None, None] => Ok(vec![random_canonical_bigint(), random_canonical_bigint()]),
+        [Some(_), None] => Err(GrapevineError::Serialization(String::from(
+            "a previous username was given without a current username",
+        ))),
+    }
+}
+
+/**
+ * Serializes the username of a logic step, which the circuit constrains to be non-zero
+ *
+ * @param username - the username to serialize
+ * @return - the serialized username, or an error if it is too long or serializes to 0
+ */
+fn username_input(username: &String) -> Result<BigInt, GrapevineError> {
+    let serialized =
+        serialize_username(username).map_err(|e| GrapevineError::Serialization(e.to_string()))?;
+    // empty usernames and usernames of only null bytes both serialize to 0
+    if serialized == BigInt::from(0) {
+        return Err(GrapevineError::Serialization(String::from(
+            "usernames must not be empty",
+        )));
+    }
+    Ok(serialized)
+}
+
+/** Marshals the salt and siblings of a path insertion, or random values for chaff steps */
+fn path_inputs(path: &Option<PathInsertion>) -> Vec<BigInt> {
+    let values = match path {
+        Some(path) => [vec![path.salt], path.siblings.to_vec()].concat(),
+        None => (0..1 + PATH_TREE_DEPTH).map(|_| random_fr()).collect(),
+    };
+    values
+        .iter()
+        .map(|v| BigInt::from_bytes_le(Plus, &v.into_bigint().to_bytes_le()))
+        .collect()
+}
+
+/** Converts bigints to field elements */
+fn to_field_elements<F: PrimeField>(values: &[BigInt]) -> Vec<F> {
+    values
+        .iter()
         .map(|v| {
             let (_, b) = v.to_bytes_le();
             F::from_le_bytes_mod_order(&b)
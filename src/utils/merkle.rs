@@ -0,0 +1,81 @@
+use ark_bn254::Fr;
+use ark_ff::Zero;
+
+use super::hash::poseidon;
+
+/**
+ * Roots of empty subtrees of each height, where the empty leaf is 0
+ *
+ * @param depth - the depth of the tree
+ * @return - the empty roots from the leaf level (0) up to the root level (depth)
+ */
+pub fn empty_subtree_roots(depth: usize) -> Vec<Fr> {
+    let mut roots = vec![Fr::zero(); depth + 1];
+    for level in 0..depth {
+        roots[level + 1] = poseidon(&[roots[level], roots[level]]);
+    }
+    roots
+}
+
+/**
+ * Computes the root of a fixed depth binary poseidon merkle tree
+ *
+ * @param leaves - the leaves of the tree, followed by empty slots up to its capacity
+ * @param depth - the depth of the tree
+ * @return - the root of the tree
+ */
+pub fn merkle_root(leaves: &[Fr], depth: usize) -> Fr {
+    let empty = empty_subtree_roots(depth);
+    let mut layer = leaves.to_vec();
+    for level in 0..depth {
+        layer = next_layer(&layer, empty[level]);
+    }
+    layer.first().copied().unwrap_or(empty[depth])
+}
+
+/**
+ * Computes the siblings of a slot of a fixed depth binary poseidon merkle tree
+ *
+ * @param leaves - the leaves of the tree, followed by empty slots up to its capacity
+ * @param index - the index of the slot, which may be empty
+ * @param depth - the depth of the tree
+ * @return - the siblings from the leaf level up to the root
+ */
+pub fn merkle_siblings(leaves: &[Fr], index: usize, depth: usize) -> Vec<Fr> {
+    let empty = empty_subtree_roots(depth);
+    let mut siblings = Vec::with_capacity(depth);
+    let mut layer = leaves.to_vec();
+    let mut index = index;
+    for level in 0..depth {
+        siblings.push(*layer.get(index ^ 1).unwrap_or(&empty[level]));
+        layer = next_layer(&layer, empty[level]);
+        index >>= 1;
+    }
+    siblings
+}
+
+/**
+ * Computes a root from a leaf and its siblings as done by the PathTreeRoot template
+ *
+ * @param leaf - the leaf
+ * @param index - the index of the leaf
+ * @param siblings - the siblings from the leaf level up to the root
+ * @return - the root
+ */
+pub fn compute_root(leaf: Fr, index: usize, siblings: &[Fr]) -> Fr {
+    siblings
+        .iter()
+        .enumerate()
+        .fold(leaf, |node, (level, sibling)| match (index >> level) & 1 {
+            0 => poseidon(&[node, *sibling]),
+            _ => poseidon(&[*sibling, node]),
+        })
+}
+
+/** Hashes pairs of nodes of a layer, padding an odd layer with the empty node */
+fn next_layer(layer: &[Fr], empty: Fr) -> Vec<Fr> {
+    layer
+        .chunks(2)
+        .map(|pair| poseidon(&[pair[0], *pair.get(1).unwrap_or(&empty)]))
+        .collect()
+}
@@ -2,11 +2,35 @@ use ark_bn254::Fr;
 use ark_ff::UniformRand;
 use ark_std::rand::rngs::OsRng;
 
+pub mod babyjubjub;
+pub mod eddsa;
+#[cfg(test)]
+pub(crate) mod fixtures;
+pub mod generators;
+pub mod hash;
 pub mod inputs;
+pub mod merkle;
+pub mod path;
+pub mod phrase_set;
+pub mod reference;
 
+// version of the grapevine circuits, bumped when the statement changes (v2 domain separates hashes)
+pub const CIRCUIT_VERSION: u32 = 2;
 pub const SECRET_FIELD_LENGTH: usize = 6;
 pub const MAX_SECRET_LENGTH: usize = 180;
 pub const MAX_USERNAME_LENGTH: usize = 30;
+// phrase chunks and usernames are packed into 31 bytes, so canonical encodings fit in 248 bits
+pub const CANONICAL_FELT_BITS: usize = 248;
+pub const IVC_STATE_LENGTH: usize = 6;
+pub const PATH_TREE_DEPTH: usize = 8;
+pub const EXTERNAL_INPUTS_LENGTH: usize = SECRET_FIELD_LENGTH + 2 + 2 + 1 + PATH_TREE_DEPTH;
+// phrase, usernames, previous public key, link signature (R8, S), public key, path salt/siblings
+pub const EDDSA_EXTERNAL_INPUTS_LENGTH: usize =
+    SECRET_FIELD_LENGTH + 2 + 2 + 3 + 2 + 1 + PATH_TREE_DEPTH;
+pub const PHRASE_SET_DEPTH: usize = 16;
+// grapevine inputs, shared phrase hash, phrase index and siblings in the phrase set
+pub const ANONYMOUS_EXTERNAL_INPUTS_LENGTH: usize =
+    EXTERNAL_INPUTS_LENGTH + 1 + 1 + PHRASE_SET_DEPTH;
 
 /** Get a random field element */
 pub fn random_fr() -> ark_bn254::Fr {
@@ -0,0 +1,369 @@
+use ark_bn254::Fr;
+use ark_ff::{PrimeField, Zero};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use num_bigint::BigInt;
+use std::collections::HashSet;
+use std::error::Error;
+
+use super::hash::{path_accumulator, path_leaf, path_salt};
+use super::inputs::{decode_z_i, serialize_username};
+use super::merkle::{self, empty_subtree_roots, merkle_root, merkle_siblings};
+use super::PATH_TREE_DEPTH;
+use crate::errors::GrapevineError;
+
+/** Salt and siblings inserting the prover of a logic step into the path tree */
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathInsertion {
+    pub salt: Fr,
+    pub siblings: [Fr; PATH_TREE_DEPTH],
+}
+
+/** Proof that a username is committed in the path tree of a chain */
+#[derive(Clone, Debug, PartialEq)]
+pub struct MembershipProof {
+    // position of the user on the path, 0 for the degree 1 user
+    pub index: usize,
+    pub username: String,
+    pub salt: Fr,
+    pub siblings: [Fr; PATH_TREE_DEPTH],
+}
+
+/**
+ * Merkle tree of the salted usernames on a path, as committed to by the circuit
+ * @dev the tree has a fixed depth of PATH_TREE_DEPTH with empty slots set to 0, and the user at
+ *      degree N is inserted at index N - 1
+ */
+#[derive(Clone, Debug, Default, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PathTree {
+    pub leaves: Vec<Fr>,
+}
+
+impl PathTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /** Computes the root of the tree */
+    pub fn root(&self) -> Fr {
+        merkle_root(&self.leaves, PATH_TREE_DEPTH)
+    }
+
+    /**
+     * Computes the siblings of a slot of the tree
+     *
+     * @param index - the index of the slot, which may be empty
+     * @return - the siblings from the leaf level up to the root
+     */
+    pub fn siblings(&self, index: usize) -> [Fr; PATH_TREE_DEPTH] {
+        merkle_siblings(&self.leaves, index, PATH_TREE_DEPTH)
+            .try_into()
+            .unwrap()
+    }
+
+    /**
+     * Appends a user to the tree
+     *
+     * @param username - the username of the user
+     * @param salt - the salt of the user's leaf, see `hash::path_salt`
+     * @return - the salt and siblings the circuit needs to insert the user
+     */
+    pub fn insert(&mut self, username: &String, salt: Fr) -> Result<PathInsertion, GrapevineError> {
+        if self.leaves.len() >= 1 << PATH_TREE_DEPTH {
+            return Err(GrapevineError::InvalidPath(String::from(
+                "path tree is full",
+            )));
+        }
+        let username = Fr::from(
+            serialize_username(username)
+                .map_err(|e| GrapevineError::InvalidPath(e.to_string()))?
+                .to_biguint()
+                .unwrap(),
+        );
+        let siblings = self.siblings(self.leaves.len());
+        self.leaves.push(path_leaf(username, salt));
+        Ok(PathInsertion { salt, siblings })
+    }
+
+    /**
+     * Appends a user to the tree with a salt derived from their secret
+     *
+     * @param username - the username of the user
+     * @param secret - the auth secret (or eddsa private key scalar) of the user
+     * @param phrase_hash - the phrase hash of the chain
+     * @return - the salt and siblings the circuit needs to insert the user
+     */
+    pub fn insert_user(
+        &mut self,
+        username: &String,
+        secret: &BigInt,
+        phrase_hash: Fr,
+    ) -> Result<PathInsertion, GrapevineError> {
+        let salt = derive_path_salt(secret, phrase_hash, username)?;
+        self.insert(username, salt)
+    }
+
+    /**
+     * Proves that a user is committed in the tree without revealing the other users
+     *
+     * @param username - the username of the user
+     * @param salt - the salt of the user's leaf
+     * @return - the membership proof, or None if the user is not in the tree
+     */
+    pub fn prove_membership(&self, username: &String, salt: Fr) -> Option<MembershipProof> {
+        let leaf = path_leaf(
+            Fr::from(serialize_username(username).ok()?.to_biguint().unwrap()),
+            salt,
+        );
+        let index = self.leaves.iter().position(|l| *l == leaf)?;
+        Some(MembershipProof {
+            index,
+            username: username.clone(),
+            salt,
+            siblings: self.siblings(index),
+        })
+    }
+}
+
+/**
+ * Derives the salt of a user's leaf, so that users can disclose their membership later without
+ * storing anything but their secret
+ *
+ * @param secret - the auth secret (or eddsa private key scalar) of the user
+ * @param phrase_hash - the phrase hash of the chain (z_i[1])
+ * @param username - the username of the user
+ * @return - the salt of the user's leaf in the path tree of the chain
+ */
+pub fn derive_path_salt(
+    secret: &BigInt,
+    phrase_hash: Fr,
+    username: &String,
+) -> Result<Fr, GrapevineError> {
+    let username =
+        serialize_username(username).map_err(|e| GrapevineError::InvalidPath(e.to_string()))?;
+    let (_, bytes) = secret.to_bytes_le();
+    Ok(path_salt(
+        Fr::from_le_bytes_mod_order(&bytes),
+        phrase_hash,
+        Fr::from(username.to_biguint().unwrap()),
+    ))
+}
+
+/** Root of the empty path tree, the initial value of the path root in the ivc state */
+pub fn empty_path_root() -> Fr {
+    empty_subtree_roots(PATH_TREE_DEPTH)[PATH_TREE_DEPTH]
+}
+
+/**
+ * Computes a root from a leaf and its siblings as done by the circuit
+ *
+ * @param leaf - the leaf
+ * @param index - the index of the leaf
+ * @param siblings - the siblings from the leaf level up to the root
+ * @return - the root
+ */
+pub fn compute_root(leaf: Fr, index: usize, siblings: &[Fr; PATH_TREE_DEPTH]) -> Fr {
+    merkle::compute_root(leaf, index, siblings)
+}
+
+/**
+ * Verifies that a user is committed in the path of a chain
+ *
+ * @param z_i - the ivc state of the chain
+ * @param proof - the membership proof disclosed by the user
+ */
+pub fn verify_membership(z_i: &[Fr], proof: &MembershipProof) -> Result<(), GrapevineError> {
+    let output = decode_z_i(z_i)
+        .ok_or_else(|| GrapevineError::InvalidPath(String::from("malformed ivc state")))?;
+    // slots past the degree of the chain were never inserted by the circuit
+    if proof.index as u64 >= output.degree {
+        return Err(GrapevineError::InvalidPath(format!(
+            "index {} is past the degree {} of the chain",
+            proof.index, output.degree
+        )));
+    }
+    let username = serialize_username(&proof.username)
+        .map_err(|e| GrapevineError::InvalidPath(e.to_string()))?;
+    let leaf = path_leaf(Fr::from(username.to_biguint().unwrap()), proof.salt);
+    if compute_root(leaf, proof.index, &proof.siblings) != output.path_root {
+        return Err(GrapevineError::InvalidPath(format!(
+            "{} is not committed in the path",
+            proof.username
+        )));
+    }
+    Ok(())
+}
+
+/**
+ * Recomputes the path accumulator of a chain from its users and their leaf salts
+ *
+ * @param path - the usernames on the path with their salts, starting with the degree 1 user
+ * @return - the path accumulator output by the degree of the last user
+ */
+pub fn accumulate_path(path: &[(String, Fr)]) -> Result<Fr, Box<dyn Error>> {
+    path.iter()
+        .try_fold(Fr::zero(), |accumulator, (username, salt)| {
+            let username = Fr::from(serialize_username(username)?.to_biguint().unwrap());
+            Ok(path_accumulator(accumulator, path_leaf(username, *salt)))
+        })
+}
+
+/**
+ * Checks a disclosed path against the ivc state of a chain
+ * @dev fails if the path is not the one accumulated by the chain, or if it visits a user twice.
+ *      the accumulator is salted, so auditing needs the salt of every user on the path
+ *
+ * @param z_i - the ivc state of the chain
+ * @param path - the disclosed usernames on the path with their salts, starting with the degree
+ *               1 user
+ */
+pub fn audit_path(z_i: &[Fr], path: &[(String, Fr)]) -> Result<(), GrapevineError> {
+    let output = decode_z_i(z_i)
+        .ok_or_else(|| GrapevineError::InvalidPath(String::from("malformed ivc state")))?;
+    if path.len() as u64 != output.degree {
+        return Err(GrapevineError::InvalidPath(format!(
+            "{} usernames given for a chain of degree {}",
+            path.len(),
+            output.degree
+        )));
+    }
+    let accumulator =
+        accumulate_path(path).map_err(|e| GrapevineError::InvalidPath(e.to_string()))?;
+    if accumulator != output.path_accumulator {
+        return Err(GrapevineError::InvalidPath(String::from(
+            "usernames do not match the path accumulator",
+        )));
+    }
+    let mut visited = HashSet::new();
+    if let Some((username, _)) = path.iter().find(|(u, _)| !visited.insert(u)) {
+        return Err(GrapevineError::InvalidPath(format!(
+            "{} appears more than once",
+            username
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::random_fr;
+
+    fn state(path: &[(String, Fr)]) -> Vec<Fr> {
+        let accumulator = accumulate_path(path).unwrap();
+        vec![
+            Fr::from(path.len() as u64),
+            Fr::zero(),
+            Fr::zero(),
+            Fr::zero(),
+            accumulator,
+            empty_path_root(),
+        ]
+    }
+
+    /// Pairs usernames with the salts 1, 2, ...
+    fn salted(usernames: &[&str]) -> Vec<(String, Fr)> {
+        usernames
+            .iter()
+            .enumerate()
+            .map(|(i, username)| (String::from(*username), Fr::from(i as u64 + 1)))
+            .collect()
+    }
+
+    #[test]
+    fn test_audit_path() {
+        let path = salted(&["alice", "bob", "charlie"]);
+        let z_i = state(&path);
+        assert!(audit_path(&z_i, &path).is_ok());
+
+        // reordered, truncated or substituted paths are rejected, as are wrong salts
+        let reordered = [path[1].clone(), path[0].clone(), path[2].clone()];
+        assert!(audit_path(&z_i, &reordered).is_err());
+        assert!(audit_path(&z_i, &path[..2]).is_err());
+        let substituted = salted(&["alice", "mallory", "charlie"]);
+        assert!(audit_path(&z_i, &substituted).is_err());
+        let mut resalted = path.clone();
+        resalted[1].1 = Fr::from(42);
+        assert!(audit_path(&z_i, &resalted).is_err());
+
+        // a chain that revisits a user is detected
+        let looped = salted(&["alice", "bob", "alice"]);
+        let result = audit_path(&state(&looped), &looped);
+        assert!(matches!(result, Err(GrapevineError::InvalidPath(msg)) if msg.contains("alice")));
+    }
+
+    #[test]
+    fn test_path_hidden_without_salts() {
+        let salts = [random_fr(), random_fr()];
+        let path = [
+            (String::from("alice"), salts[0]),
+            (String::from("bob"), salts[1]),
+        ];
+        let z_i = state(&path);
+
+        // guessing the path one degree at a time from a list of usernames, hashed as they are or
+        // with a zero salt, never reaches the accumulator when the salts are unknown
+        let candidates = ["alice", "bob", "charlie", "mallory"].map(|username| {
+            Fr::from(
+                serialize_username(&String::from(username))
+                    .unwrap()
+                    .to_biguint()
+                    .unwrap(),
+            )
+        });
+        let mut accumulators = vec![Fr::zero()];
+        for _ in 0..path.len() {
+            accumulators = accumulators
+                .iter()
+                .flat_map(|accumulator| {
+                    candidates.iter().flat_map(move |username| {
+                        [
+                            path_accumulator(*accumulator, *username),
+                            path_accumulator(*accumulator, path_leaf(*username, Fr::zero())),
+                        ]
+                    })
+                })
+                .collect();
+        }
+        assert!(!accumulators.contains(&z_i[4]));
+
+        // while the users' salts open it
+        assert!(audit_path(&z_i, &path).is_ok());
+    }
+
+    #[test]
+    fn test_path_tree_membership() {
+        let usernames = ["alice", "bob", "charlie"].map(String::from);
+        let salts = [Fr::from(1), Fr::from(2), Fr::from(3)];
+        let mut tree = PathTree::new();
+        assert_eq!(tree.root(), empty_path_root());
+        for (username, salt) in usernames.iter().zip(salts) {
+            // the slot is empty before the insertion and holds the leaf after it
+            let root = tree.root();
+            let index = tree.leaves.len();
+            let insertion = tree.insert(username, salt).unwrap();
+            assert_eq!(compute_root(Fr::zero(), index, &insertion.siblings), root);
+            assert_eq!(
+                compute_root(tree.leaves[index], index, &insertion.siblings),
+                tree.root()
+            );
+        }
+        let salted = usernames.iter().cloned().zip(salts).collect::<Vec<_>>();
+        let mut z_i = state(&salted);
+        z_i[5] = tree.root();
+
+        let proof = tree.prove_membership(&usernames[1], salts[1]).unwrap();
+        assert_eq!(proof.index, 1);
+        assert!(verify_membership(&z_i, &proof).is_ok());
+
+        // a wrong salt or username does not open the commitment
+        assert!(tree.prove_membership(&usernames[1], salts[0]).is_none());
+        let forged = MembershipProof {
+            username: String::from("mallory"),
+            ..proof.clone()
+        };
+        assert!(verify_membership(&z_i, &forged).is_err());
+        // nor does a proof for a slot past the degree of the chain
+        z_i[0] = Fr::from(1);
+        assert!(verify_membership(&z_i, &proof).is_err());
+    }
+}
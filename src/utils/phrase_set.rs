@@ -0,0 +1,126 @@
+use ark_bn254::Fr;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+use super::inputs::hash_phrase;
+use super::merkle::{compute_root, empty_subtree_roots, merkle_root, merkle_siblings};
+use super::PHRASE_SET_DEPTH;
+use crate::errors::GrapevineError;
+
+/** Index and siblings proving that a phrase hash is registered in a phrase set */
+#[derive(Clone, Debug, PartialEq)]
+pub struct PhraseWitness {
+    pub index: usize,
+    pub siblings: [Fr; PHRASE_SET_DEPTH],
+}
+
+/**
+ * Public merkle tree of registered phrase hashes, whose root is the phrase set root that the
+ * anonymous grapevine circuit carries in place of the phrase hash
+ * @dev the tree has a fixed depth of PHRASE_SET_DEPTH with empty slots set to 0
+ */
+#[derive(Clone, Debug, Default, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PhraseSet {
+    pub leaves: Vec<Fr>,
+}
+
+impl PhraseSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+     * Builds a phrase set from the registered phrases
+     *
+     * @param phrases - the registered phrases
+     * @return - the phrase set containing the hash of each phrase
+     */
+    pub fn from_phrases(phrases: &[String]) -> Result<Self, GrapevineError> {
+        let mut set = Self::new();
+        for phrase in phrases {
+            let phrase_hash =
+                hash_phrase(phrase).map_err(|e| GrapevineError::InvalidPhraseSet(e.to_string()))?;
+            set.register(phrase_hash)?;
+        }
+        Ok(set)
+    }
+
+    /**
+     * Registers a phrase hash
+     *
+     * @param phrase_hash - the hash of the phrase, see `hash_phrase`
+     * @return - the index of the phrase hash in the set
+     */
+    pub fn register(&mut self, phrase_hash: Fr) -> Result<usize, GrapevineError> {
+        if let Some(index) = self.leaves.iter().position(|leaf| *leaf == phrase_hash) {
+            return Ok(index);
+        }
+        if self.leaves.len() >= 1 << PHRASE_SET_DEPTH {
+            return Err(GrapevineError::InvalidPhraseSet(String::from(
+                "phrase set is full",
+            )));
+        }
+        self.leaves.push(phrase_hash);
+        Ok(self.leaves.len() - 1)
+    }
+
+    /** Computes the root of the phrase set */
+    pub fn root(&self) -> Fr {
+        merkle_root(&self.leaves, PHRASE_SET_DEPTH)
+    }
+
+    /**
+     * Computes the witness the circuit needs to prove a phrase is registered
+     *
+     * @param phrase_hash - the hash of the phrase
+     * @return - the witness, or None if the phrase is not registered
+     */
+    pub fn witness(&self, phrase_hash: Fr) -> Option<PhraseWitness> {
+        let index = self.leaves.iter().position(|leaf| *leaf == phrase_hash)?;
+        Some(PhraseWitness {
+            index,
+            siblings: merkle_siblings(&self.leaves, index, PHRASE_SET_DEPTH)
+                .try_into()
+                .unwrap(),
+        })
+    }
+}
+
+/** Root of the empty phrase set */
+pub fn empty_phrase_set_root() -> Fr {
+    empty_subtree_roots(PHRASE_SET_DEPTH)[PHRASE_SET_DEPTH]
+}
+
+/**
+ * Checks a phrase witness natively as done by the anonymous grapevine circuit
+ *
+ * @param root - the phrase set root
+ * @param phrase_hash - the hash of the phrase
+ * @param witness - the witness of the phrase hash in the set
+ * @return - true if the phrase hash is registered at the witnessed index
+ */
+pub fn verify_phrase_witness(root: Fr, phrase_hash: Fr, witness: &PhraseWitness) -> bool {
+    witness.index < 1 << PHRASE_SET_DEPTH
+        && compute_root(phrase_hash, witness.index, &witness.siblings) == root
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_phrase_set_witness() {
+        let phrases = ["first phrase", "second phrase", "third phrase"].map(String::from);
+        let set = PhraseSet::from_phrases(&phrases).unwrap();
+        assert_ne!(set.root(), empty_phrase_set_root());
+
+        let phrase_hash = hash_phrase(&phrases[1]).unwrap();
+        let witness = set.witness(phrase_hash).unwrap();
+        assert_eq!(witness.index, 1);
+        assert!(verify_phrase_witness(set.root(), phrase_hash, &witness));
+
+        // unregistered phrases have no witness, and witnesses do not transfer to them
+        let unregistered = hash_phrase(&String::from("unregistered phrase")).unwrap();
+        assert!(set.witness(unregistered).is_none());
+        assert!(!verify_phrase_witness(set.root(), unregistered, &witness));
+    }
+}
@@ -0,0 +1,248 @@
+use ark_bn254::Fr;
+use ark_ff::{One, PrimeField, Zero};
+
+use super::hash::{
+    degree_secret_hash, path_accumulator, path_leaf, phrase_hash, relationship_secret,
+};
+use super::inputs::check_canonical_inputs;
+use super::path::compute_root;
+use super::{EXTERNAL_INPUTS_LENGTH, IVC_STATE_LENGTH, PATH_TREE_DEPTH, SECRET_FIELD_LENGTH};
+
+/**
+ * Pure rust reference of a single step of the grapevine circuit
+ *
+ * @param z_i - the ivc state before the step
+ * @param external_inputs - the external inputs of the step, laid out as in the circuit
+ * @return - the ivc state after the step, or None if the circuit would be unsatisfiable
+ */
+pub fn grapevine_step(z_i: &[Fr], external_inputs: &[Fr]) -> Option<Vec<Fr>> {
+    if z_i.len() != IVC_STATE_LENGTH || external_inputs.len() != EXTERNAL_INPUTS_LENGTH {
+        return None;
+    }
+    let (degree, given_phrase_hash, given_degree_secret_hash, is_chaff) =
+        (z_i[0], z_i[1], z_i[2], z_i[3]);
+    let (given_path_accumulator, given_path_root) = (z_i[4], z_i[5]);
+    let phrase: [Fr; SECRET_FIELD_LENGTH] =
+        external_inputs[..SECRET_FIELD_LENGTH].try_into().unwrap();
+    let usernames = &external_inputs[SECRET_FIELD_LENGTH..SECRET_FIELD_LENGTH + 2];
+    let prev_relationship_secret = external_inputs[SECRET_FIELD_LENGTH + 2];
+    let auth_secret = external_inputs[SECRET_FIELD_LENGTH + 3];
+    let path_salt = external_inputs[SECRET_FIELD_LENGTH + 4];
+    let path_siblings: [Fr; PATH_TREE_DEPTH] = external_inputs[SECRET_FIELD_LENGTH + 5..]
+        .try_into()
+        .unwrap();
+
+    // phrase chunks and usernames must be range checked encodings of strings
+    check_canonical_inputs(external_inputs).ok()?;
+
+    // chaff flag must be boolean
+    if !is_chaff.is_zero() && !is_chaff.is_one() {
+        return None;
+    }
+    let is_chaff = is_chaff.is_one();
+    // the all-zero step sonobe synthesizes for the r1cs shape skips the checks of logic steps
+    let is_logic = !is_chaff && !given_path_root.is_zero();
+
+    // the previous username is 0 exactly at degree 0, and logic steps need a current username
+    if degree.is_zero() && !usernames[0].is_zero() {
+        return None;
+    }
+    if is_logic && (usernames[1].is_zero() || (!degree.is_zero() && usernames[0].is_zero())) {
+        return None;
+    }
+
+    // degree 0 hashes the phrase, later degrees reuse the given phrase hash
+    let computed_phrase_hash = match degree.is_zero() {
+        true => phrase_hash(&phrase),
+        false => given_phrase_hash,
+    };
+
+    // past degree 0, the previous user must open the given degree secret hash unless chaff
+    let opened = degree_secret_hash(computed_phrase_hash, usernames[0], prev_relationship_secret);
+    if !degree.is_zero() && !is_chaff && opened != given_degree_secret_hash {
+        return None;
+    }
+
+    // logic steps index the path tree by the degree, which caps chains at 2^PATH_TREE_DEPTH
+    // degrees, while other steps use slot 0
+    if is_logic && degree >= Fr::from(1u64 << PATH_TREE_DEPTH) {
+        return None;
+    }
+    let index = match is_logic {
+        true => degree.into_bigint().as_ref()[0] as usize,
+        false => 0,
+    };
+    // in logic steps, the slot at the degree must be empty in the given path tree
+    if is_logic && compute_root(Fr::zero(), index, &path_siblings) != given_path_root {
+        return None;
+    }
+
+    match is_chaff {
+        true => Some(vec![
+            degree,
+            given_phrase_hash,
+            given_degree_secret_hash,
+            Fr::zero(),
+            given_path_accumulator,
+            given_path_root,
+        ]),
+        false => Some(vec![
+            degree + Fr::one(),
+            computed_phrase_hash,
+            degree_secret_hash(
+                computed_phrase_hash,
+                usernames[1],
+                relationship_secret(auth_secret, computed_phrase_hash),
+            ),
+            Fr::one(),
+            path_accumulator(given_path_accumulator, path_leaf(usernames[1], path_salt)),
+            compute_root(path_leaf(usernames[1], path_salt), index, &path_siblings),
+        ]),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::circom::{grapevine_circuit, grapevine_native_circuit};
+    use crate::utils::fixtures::alice_degree_1;
+    use crate::utils::inputs::{
+        derive_relationship_secret, get_z0, prepare_external_inputs, CircomPrivateInput,
+    };
+    use crate::utils::path::{accumulate_path, PathTree};
+    use crate::witness::NativeCircomFCircuit;
+    use ark_ff::{BigInteger, Field};
+    use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar, R1CSVar};
+    use ark_relations::r1cs::ConstraintSystem;
+    use lazy_static::lazy_static;
+    use num_bigint::{BigInt, Sign};
+    use proptest::prelude::*;
+    use sonobe::frontend::{circom::CircomFCircuit, FCircuit};
+    use std::path::PathBuf;
+
+    lazy_static! {
+        static ref CIRCOM_CIRCUIT: CircomFCircuit<Fr> = grapevine_circuit(
+            PathBuf::from("./circom/artifacts/grapevine.r1cs"),
+            PathBuf::from("./circom/artifacts/grapevine.wasm"),
+        )
+        .unwrap();
+        static ref NATIVE_CIRCUIT: NativeCircomFCircuit<Fr> =
+            grapevine_native_circuit(PathBuf::from("./circom/artifacts/grapevine.r1cs")).unwrap();
+    }
+
+    /// Runs a step through every implementation and checks they agree
+    fn differential_step(i: usize, z_i: &[Fr], inputs: &CircomPrivateInput) -> Vec<Fr> {
+        let external_inputs = prepare_external_inputs::<Fr>(inputs).unwrap();
+        let expected = grapevine_step(z_i, &external_inputs).unwrap();
+
+        let circom = CIRCOM_CIRCUIT
+            .step_native(i, z_i.to_vec(), external_inputs.clone())
+            .unwrap();
+        assert_eq!(circom, expected);
+        let native = NATIVE_CIRCUIT
+            .step_native(i, z_i.to_vec(), external_inputs.clone())
+            .unwrap();
+        assert_eq!(native, expected);
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let z_i_var = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i.to_vec())).unwrap();
+        let external_inputs_var =
+            Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(external_inputs)).unwrap();
+        let z_i1_var = CIRCOM_CIRCUIT
+            .generate_step_constraints(cs.clone(), i, z_i_var, external_inputs_var)
+            .unwrap();
+        assert_eq!(z_i1_var.value().unwrap(), expected);
+        assert!(cs.is_satisfied().unwrap());
+
+        expected
+    }
+
+    fn secret() -> impl Strategy<Value = BigInt> {
+        any::<[u8; 32]>().prop_map(|bytes| {
+            let fr = Fr::from_le_bytes_mod_order(&bytes);
+            BigInt::from_bytes_le(Sign::Plus, &fr.into_bigint().to_bytes_le())
+        })
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(8))]
+
+        #[test]
+        fn test_differential_degrees(
+            phrase in "[a-zA-Z0-9 ]{1,180}",
+            usernames in ["[a-z0-9_]{1,30}", "[a-z0-9_]{1,30}"],
+            auth_secrets in [secret(), secret()],
+        ) {
+            // degree 1 logic step and chaff step
+            let mut path = PathTree::new();
+            let steps = [
+                CircomPrivateInput {
+                    phrase: Some(phrase),
+                    usernames: [None, Some(usernames[0].clone())],
+                    relationship_secret: None,
+                    auth_secret: Some(auth_secrets[0].clone()),
+                    path: Some(path.insert(&usernames[0], Fr::from(1)).unwrap()),
+                    chaff: false,
+                },
+                CircomPrivateInput::empty(true),
+            ];
+            let mut z_i = get_z0().to_vec();
+            for (i, inputs) in steps.iter().enumerate() {
+                z_i = differential_step(i, &z_i, inputs);
+            }
+            // alice only hands bob the secret derived for this phrase
+            let steps = [
+                CircomPrivateInput {
+                    phrase: None,
+                    usernames: [Some(usernames[0].clone()), Some(usernames[1].clone())],
+                    relationship_secret: Some(derive_relationship_secret(&auth_secrets[0], z_i[1])),
+                    auth_secret: Some(auth_secrets[1].clone()),
+                    path: Some(path.insert(&usernames[1], Fr::from(2)).unwrap()),
+                    chaff: false,
+                },
+                CircomPrivateInput::empty(true),
+            ];
+            for (i, inputs) in steps.iter().enumerate() {
+                z_i = differential_step(i + 2, &z_i, inputs);
+            }
+            prop_assert_eq!(z_i[0], Fr::from(2));
+            prop_assert_eq!(z_i[3], Fr::zero());
+            let salted = [
+                (usernames[0].clone(), Fr::from(1)),
+                (usernames[1].clone(), Fr::from(2)),
+            ];
+            prop_assert_eq!(z_i[4], accumulate_path(&salted).unwrap());
+            prop_assert_eq!(z_i[5], path.root());
+        }
+    }
+
+    #[test]
+    fn test_chaff_past_path_capacity() {
+        // the last degree the path tree has room for is followed by its chaff step
+        let mut z_i = (1..=IVC_STATE_LENGTH as u64)
+            .map(Fr::from)
+            .collect::<Vec<Fr>>();
+        z_i[0] = Fr::from(1u64 << PATH_TREE_DEPTH);
+        z_i[3] = Fr::one();
+        let z_i1 = differential_step(1, &z_i, &CircomPrivateInput::empty(true));
+        z_i[3] = Fr::zero();
+        assert_eq!(z_i1, z_i);
+    }
+
+    #[test]
+    fn test_non_canonical_username() {
+        let (inputs, _) = alice_degree_1();
+        let mut external_inputs = prepare_external_inputs::<Fr>(&inputs).unwrap();
+        let z_0 = get_z0().to_vec();
+        assert!(grapevine_step(&z_0, &external_inputs).is_some());
+
+        // the username plus 2^248 is no longer a canonical 31 byte encoding
+        external_inputs[SECRET_FIELD_LENGTH + 1] += Fr::from(2u64).pow([248]);
+        assert!(check_canonical_inputs(&external_inputs).is_err());
+        assert!(grapevine_step(&z_0, &external_inputs).is_none());
+        assert!(CIRCOM_CIRCUIT
+            .step_native(0, z_0.clone(), external_inputs.clone())
+            .is_err());
+        assert!(NATIVE_CIRCUIT.step_native(0, z_0, external_inputs).is_err());
+    }
+}
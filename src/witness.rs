@@ -0,0 +1,556 @@
+//! Witness generation for compiled circom circuits without the wasm runtime
+//!
+//! The witness is computed by propagating known wire values through the constraints of the
+//! compiled r1cs, so the resulting vector is indexed exactly like the r1cs wires. Besides
+//! constraints that are linear in their single unknown wire, the solver understands the two
+//! kinds of hints circomlib relies on: inverses that are zero when their input is zero (IsZero)
+//! and bit decompositions of a value into boolean-constrained wires (Num2Bits).
+
+use ark_ff::PrimeField;
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    fields::fp::{AllocatedFp, FpVar},
+    R1CSVar,
+};
+use ark_relations::{
+    lc,
+    r1cs::{ConstraintSystemRef, LinearCombination, SynthesisError, Variable},
+};
+use num_bigint::BigUint;
+use sonobe::{frontend::FCircuit, Error};
+use std::{collections::VecDeque, fs, io::Read, path::Path, sync::Arc};
+
+use crate::errors::GrapevineError;
+
+pub type LinearTerms<F> = Vec<(usize, F)>;
+pub type Constraint<F> = (LinearTerms<F>, LinearTerms<F>, LinearTerms<F>);
+
+/** A circom r1cs file parsed into wire indexed constraints */
+#[derive(Clone, Debug)]
+pub struct CircomR1CS<F: PrimeField> {
+    pub n_wires: usize,
+    pub n_pub_out: usize,
+    pub n_pub_in: usize,
+    pub n_prv_in: usize,
+    pub constraints: Vec<Constraint<F>>,
+}
+
+fn read_u32(reader: &mut &[u8]) -> Result<u32, GrapevineError> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(reader: &mut &[u8]) -> Result<u64, GrapevineError> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_terms<F: PrimeField>(
+    reader: &mut &[u8],
+    n8: usize,
+) -> Result<LinearTerms<F>, GrapevineError> {
+    let n_terms = read_u32(reader)?;
+    let mut coeff = vec![0u8; n8];
+    (0..n_terms)
+        .map(|_| {
+            let wire = read_u32(reader)? as usize;
+            reader.read_exact(&mut coeff)?;
+            Ok((wire, F::from_le_bytes_mod_order(&coeff)))
+        })
+        .collect()
+}
+
+impl<F: PrimeField> CircomR1CS<F> {
+    /**
+     * Parses a circom r1cs file
+     *
+     * @param bytes - the contents of the .r1cs file
+     * @return - the parsed r1cs
+     */
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, GrapevineError> {
+        let invalid = |msg: &str| GrapevineError::Serialization(format!("Invalid r1cs: {}", msg));
+        let mut reader = bytes;
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != b"r1cs" {
+            return Err(invalid("bad magic"));
+        }
+        let _version = read_u32(&mut reader)?;
+        let n_sections = read_u32(&mut reader)?;
+
+        // sections may appear in any order, so locate the header and constraints first
+        let mut header = None;
+        let mut constraints = None;
+        for _ in 0..n_sections {
+            let section_type = read_u32(&mut reader)?;
+            let size = read_u64(&mut reader)? as usize;
+            if reader.len() < size {
+                return Err(invalid("truncated section"));
+            }
+            let (section, rest) = reader.split_at(size);
+            match section_type {
+                1 => header = Some(section),
+                2 => constraints = Some(section),
+                _ => {}
+            }
+            reader = rest;
+        }
+
+        let mut header = header.ok_or_else(|| invalid("missing header"))?;
+        let n8 = read_u32(&mut header)? as usize;
+        let mut prime = vec![0u8; n8];
+        header.read_exact(&mut prime)?;
+        let modulus: BigUint = F::MODULUS.into();
+        if BigUint::from_bytes_le(&prime) != modulus {
+            return Err(invalid("compiled for a different prime"));
+        }
+        let n_wires = read_u32(&mut header)? as usize;
+        let n_pub_out = read_u32(&mut header)? as usize;
+        let n_pub_in = read_u32(&mut header)? as usize;
+        let n_prv_in = read_u32(&mut header)? as usize;
+        let _n_labels = read_u64(&mut header)?;
+        let n_constraints = read_u32(&mut header)? as usize;
+
+        let mut reader = constraints.ok_or_else(|| invalid("missing constraints"))?;
+        let constraints = (0..n_constraints)
+            .map(|_| {
+                Ok((
+                    read_terms(&mut reader, n8)?,
+                    read_terms(&mut reader, n8)?,
+                    read_terms(&mut reader, n8)?,
+                ))
+            })
+            .collect::<Result<Vec<Constraint<F>>, GrapevineError>>()?;
+
+        Ok(Self {
+            n_wires,
+            n_pub_out,
+            n_pub_in,
+            n_prv_in,
+            constraints,
+        })
+    }
+
+    /** Parses a circom r1cs file from disk */
+    pub fn from_file(path: &Path) -> Result<Self, GrapevineError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+}
+
+/** Known part and unknown wires of a linear combination */
+struct Evaluation<F: PrimeField> {
+    known: F,
+    unknown: Vec<(usize, F)>,
+}
+
+fn evaluate<F: PrimeField>(terms: &LinearTerms<F>, values: &[Option<F>]) -> Evaluation<F> {
+    let mut evaluation = Evaluation {
+        known: F::zero(),
+        unknown: Vec::new(),
+    };
+    for (wire, coeff) in terms {
+        match values[*wire] {
+            Some(value) => evaluation.known += *coeff * value,
+            None => match evaluation.unknown.iter_mut().find(|(w, _)| w == wire) {
+                Some((_, c)) => *c += coeff,
+                None => evaluation.unknown.push((*wire, *coeff)),
+            },
+        }
+    }
+    evaluation.unknown.retain(|(_, c)| !c.is_zero());
+    evaluation
+}
+
+/** Outcome of inspecting a single constraint */
+enum Step<F: PrimeField> {
+    Assign(Vec<(usize, F)>),
+    Satisfied,
+    Pending,
+    Unsatisfied,
+}
+
+/** Solves witnesses of a circom r1cs from its inputs */
+#[derive(Clone, Debug)]
+pub struct WitnessSolver<F: PrimeField> {
+    pub r1cs: Arc<CircomR1CS<F>>,
+    wire_constraints: Vec<Vec<usize>>,
+    boolean_wires: Vec<bool>,
+}
+
+impl<F: PrimeField> WitnessSolver<F> {
+    pub fn new(r1cs: Arc<CircomR1CS<F>>) -> Self {
+        let mut wire_constraints = vec![Vec::new(); r1cs.n_wires];
+        let mut boolean_wires = vec![false; r1cs.n_wires];
+        for (i, (a, b, c)) in r1cs.constraints.iter().enumerate() {
+            for (wire, _) in a.iter().chain(b.iter()).chain(c.iter()) {
+                if wire_constraints[*wire].last() != Some(&i) {
+                    wire_constraints[*wire].push(i);
+                }
+            }
+            // x * (x - 1) = 0 marks x as boolean
+            let single_wire = |terms: &LinearTerms<F>| {
+                let mut wires = terms.iter().map(|(w, _)| *w).filter(|w| *w != 0);
+                match (wires.next(), wires.next()) {
+                    (Some(wire), None) => Some(wire),
+                    _ => None,
+                }
+            };
+            if let (Some(x), Some(y)) = (single_wire(a), single_wire(b)) {
+                if x == y && c.iter().all(|(w, _)| *w == 0) {
+                    boolean_wires[x] = true;
+                }
+            }
+        }
+        Self {
+            r1cs,
+            wire_constraints,
+            boolean_wires,
+        }
+    }
+
+    /**
+     * Solves the full witness of the circuit
+     *
+     * @param inputs - the public inputs followed by the private inputs, in declaration order
+     * @return - the value of every wire, starting with the constant 1 and the public outputs
+     */
+    pub fn solve(&self, inputs: &[F]) -> Result<Vec<F>, GrapevineError> {
+        self.solve_with(inputs, true)
+    }
+
+    /**
+     * Propagates inputs through the constraints without failing on unsatisfied constraints
+     * @dev only meant for tests building witnesses that are known to be invalid
+     */
+    #[cfg(test)]
+    pub(crate) fn solve_unchecked(&self, inputs: &[F]) -> Vec<F> {
+        self.solve_with(inputs, false).unwrap()
+    }
+
+    fn solve_with(&self, inputs: &[F], check: bool) -> Result<Vec<F>, GrapevineError> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "solve_witness",
+            wires = self.r1cs.n_wires,
+            constraints = self.r1cs.constraints.len()
+        )
+        .entered();
+        let r1cs = &self.r1cs;
+        if inputs.len() != r1cs.n_pub_in + r1cs.n_prv_in {
+            return Err(GrapevineError::Serialization(format!(
+                "Expected {} circuit inputs, got {}",
+                r1cs.n_pub_in + r1cs.n_prv_in,
+                inputs.len()
+            )));
+        }
+        let mut values = vec![None; r1cs.n_wires];
+        values[0] = Some(F::one());
+        let first_input = 1 + r1cs.n_pub_out;
+        for (i, input) in inputs.iter().enumerate() {
+            values[first_input + i] = Some(*input);
+        }
+
+        let mut queue = (0..r1cs.constraints.len()).collect::<VecDeque<usize>>();
+        let mut queued = vec![true; r1cs.constraints.len()];
+        while let Some(i) = queue.pop_front() {
+            queued[i] = false;
+            match self.step(i, &values) {
+                Step::Assign(assignments) => {
+                    for (wire, value) in assignments {
+                        values[wire] = Some(value);
+                        for j in self.wire_constraints[wire].iter() {
+                            if !queued[*j] {
+                                queued[*j] = true;
+                                queue.push_back(*j);
+                            }
+                        }
+                    }
+                }
+                Step::Unsatisfied if check => {
+                    return Err(GrapevineError::Sonobe(format!(
+                        "Witness does not satisfy constraint {}",
+                        i
+                    )))
+                }
+                _ => {}
+            }
+        }
+
+        // wires never determined by propagation are free hints, which circomlib sets to zero
+        let witness = values
+            .into_iter()
+            .map(|value| value.unwrap_or(F::zero()))
+            .collect::<Vec<F>>();
+        if check {
+            if let Some(i) = self.first_unsatisfied(&witness) {
+                return Err(GrapevineError::Sonobe(format!(
+                    "Witness does not satisfy constraint {}",
+                    i
+                )));
+            }
+        }
+        Ok(witness)
+    }
+
+    /** Returns the index of the first constraint the witness does not satisfy */
+    pub fn first_unsatisfied(&self, witness: &[F]) -> Option<usize> {
+        let eval = |terms: &LinearTerms<F>| {
+            terms.iter().fold(F::zero(), |acc, (wire, coeff)| {
+                acc + *coeff * witness[*wire]
+            })
+        };
+        self.r1cs
+            .constraints
+            .iter()
+            .position(|(a, b, c)| eval(a) * eval(b) != eval(c))
+    }
+
+    fn step(&self, i: usize, values: &[Option<F>]) -> Step<F> {
+        let (a, b, c) = &self.r1cs.constraints[i];
+        let (a, b, c) = (
+            evaluate(a, values),
+            evaluate(b, values),
+            evaluate(c, values),
+        );
+
+        // a side that is known to be zero makes the product zero whatever the other side is
+        let product_is_zero = (a.unknown.is_empty() && a.known.is_zero())
+            || (b.unknown.is_empty() && b.known.is_zero());
+        if product_is_zero {
+            return self.solve_linear(c.known, &c.unknown);
+        }
+        if a.unknown.is_empty() && b.unknown.is_empty() {
+            // linear in the unknowns of c: a * b - c = 0
+            let unknown = c
+                .unknown
+                .iter()
+                .map(|(w, coeff)| (*w, -*coeff))
+                .collect::<Vec<_>>();
+            return self.solve_linear(a.known * b.known - c.known, &unknown);
+        }
+
+        // a single unknown wire appearing on at most one side of the product is linear
+        let mut wires = a
+            .unknown
+            .iter()
+            .chain(b.unknown.iter())
+            .chain(c.unknown.iter())
+            .map(|(w, _)| *w)
+            .collect::<Vec<usize>>();
+        wires.sort_unstable();
+        wires.dedup();
+        if wires.len() != 1 || (!a.unknown.is_empty() && !b.unknown.is_empty()) {
+            return Step::Pending;
+        }
+        let coeff = |e: &Evaluation<F>| e.unknown.first().map(|(_, c)| *c).unwrap_or(F::zero());
+        // (a0 + ax x)(b0 + bx x) = c0 + cx x with ax * bx = 0
+        let k = coeff(&a) * b.known + coeff(&b) * a.known - coeff(&c);
+        let constant = a.known * b.known - c.known;
+        self.solve_linear(constant, &[(wires[0], k)])
+    }
+
+    /** Solves constant + sum(coeff * wire) = 0 */
+    fn solve_linear(&self, constant: F, unknown: &[(usize, F)]) -> Step<F> {
+        match unknown.len() {
+            0 => match constant.is_zero() {
+                true => Step::Satisfied,
+                false => Step::Unsatisfied,
+            },
+            1 => {
+                let (wire, coeff) = unknown[0];
+                match coeff.inverse() {
+                    Some(inv) => Step::Assign(vec![(wire, -constant * inv)]),
+                    None => Step::Pending,
+                }
+            }
+            _ => self.solve_bits(constant, unknown),
+        }
+    }
+
+    /**
+     * Solves a bit decomposition: boolean wires weighted by distinct powers of two
+     * (scaled by a common factor) summing to a known value
+     */
+    fn solve_bits(&self, constant: F, unknown: &[(usize, F)]) -> Step<F> {
+        if !unknown.iter().all(|(wire, _)| self.boolean_wires[*wire]) {
+            return Step::Pending;
+        }
+        // the common factor is the weight of the lowest bit, usually listed first
+        for (_, scale) in unknown {
+            let scale_inv = scale.inverse().unwrap();
+            let weights = unknown
+                .iter()
+                .map(|(_, coeff)| (*coeff * scale_inv).into_bigint().into())
+                .collect::<Vec<BigUint>>();
+            let mut covered = BigUint::default();
+            let is_decomposition = weights.iter().all(|weight| {
+                let distinct_power = weight.count_ones() == 1 && (&covered & weight).bits() == 0;
+                covered |= weight;
+                distinct_power
+            });
+            if !is_decomposition {
+                continue;
+            }
+            // sum(2^e_i * x_i) = -constant / scale only holds if the target fits the weights
+            let target: BigUint = (-constant * scale_inv).into_bigint().into();
+            if (&target | &covered) != covered {
+                return Step::Unsatisfied;
+            }
+            let assignments = unknown
+                .iter()
+                .zip(weights.iter())
+                .map(|((wire, _), weight)| {
+                    let exponent = weight.trailing_zeros().unwrap();
+                    (*wire, F::from(target.bit(exponent)))
+                })
+                .collect();
+            return Step::Assign(assignments);
+        }
+        Step::Pending
+    }
+}
+
+/**
+ * FCircuit over a compiled circom circuit that computes witnesses natively instead of running
+ * the circuit's wasm, a drop-in replacement for sonobe's `CircomFCircuit`
+ */
+#[derive(Clone, Debug)]
+pub struct NativeCircomFCircuit<F: PrimeField> {
+    pub solver: WitnessSolver<F>,
+    state_len: usize,
+    external_inputs_len: usize,
+}
+
+impl<F: PrimeField> NativeCircomFCircuit<F> {
+    fn witness(&self, z_i: &[F], external_inputs: &[F]) -> Result<Vec<F>, GrapevineError> {
+        self.solver.solve(&[z_i, external_inputs].concat())
+    }
+}
+
+impl<F: PrimeField> FCircuit<F> for NativeCircomFCircuit<F> {
+    // (r1cs, state length, external inputs length)
+    type Params = (Arc<CircomR1CS<F>>, usize, usize);
+
+    fn new(params: Self::Params) -> Result<Self, Error> {
+        let (r1cs, state_len, external_inputs_len) = params;
+        if r1cs.n_pub_out != state_len {
+            return Err(Error::NotExpectedLength(r1cs.n_pub_out, state_len));
+        }
+        if r1cs.n_pub_in + r1cs.n_prv_in != state_len + external_inputs_len {
+            return Err(Error::NotExpectedLength(
+                r1cs.n_pub_in + r1cs.n_prv_in,
+                state_len + external_inputs_len,
+            ));
+        }
+        Ok(Self {
+            solver: WitnessSolver::new(r1cs),
+            state_len,
+            external_inputs_len,
+        })
+    }
+
+    fn state_len(&self) -> usize {
+        self.state_len
+    }
+
+    fn external_inputs_len(&self) -> usize {
+        self.external_inputs_len
+    }
+
+    fn step_native(
+        &self,
+        _i: usize,
+        z_i: Vec<F>,
+        external_inputs: Vec<F>,
+    ) -> Result<Vec<F>, Error> {
+        let witness = self
+            .witness(&z_i, &external_inputs)
+            .map_err(|_| Error::WitnessCalculationError)?;
+        Ok(witness[1..1 + self.state_len].to_vec())
+    }
+
+    fn generate_step_constraints(
+        &self,
+        cs: ConstraintSystemRef<F>,
+        _i: usize,
+        z_i: Vec<FpVar<F>>,
+        external_inputs: Vec<FpVar<F>>,
+    ) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        let values = z_i
+            .iter()
+            .chain(external_inputs.iter())
+            .map(|var| var.value())
+            .collect::<Result<Vec<F>, SynthesisError>>();
+        // values are missing when only the shape of the circuit is synthesized
+        let witness = match values {
+            Ok(values) => Some(
+                self.solver
+                    .solve(&values)
+                    .map_err(|_| SynthesisError::Unsatisfiable)?,
+            ),
+            Err(_) => None,
+        };
+        self.synthesize(cs, witness.as_deref(), &z_i, &external_inputs)
+    }
+}
+
+impl<F: PrimeField> NativeCircomFCircuit<F> {
+    /**
+     * Enforces the constraints of the circuit over a given witness
+     * @dev the witness is not checked, which lets callers synthesize invalid witnesses
+     *
+     * @param cs - the constraint system to synthesize into
+     * @param witness - the value of every wire, or None when only synthesizing the shape
+     * @param z_i - the variables of the ivc state, reused for the circuit's public inputs
+     * @param external_inputs - the variables of the external inputs
+     * @return - the variables of the next ivc state
+     */
+    pub fn synthesize(
+        &self,
+        cs: ConstraintSystemRef<F>,
+        witness: Option<&[F]>,
+        z_i: &[FpVar<F>],
+        external_inputs: &[FpVar<F>],
+    ) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        let inputs = z_i.iter().chain(external_inputs.iter()).collect::<Vec<_>>();
+        let value = |wire: usize| {
+            witness
+                .map(|w| w[wire])
+                .ok_or(SynthesisError::AssignmentMissing)
+        };
+
+        // map every wire to a linear combination in the constraint system, reusing the
+        // variables of the ivc state and external inputs for the circuit's inputs
+        let first_input = 1 + self.state_len;
+        let mut wires: Vec<LinearCombination<F>> = Vec::with_capacity(self.solver.r1cs.n_wires);
+        let mut z_i1 = Vec::with_capacity(self.state_len);
+        for wire in 0..self.solver.r1cs.n_wires {
+            if wire == 0 {
+                wires.push(lc!() + Variable::One);
+            } else if wire < first_input {
+                let var = AllocatedFp::new_witness(cs.clone(), || value(wire))?;
+                wires.push(lc!() + var.variable);
+                z_i1.push(FpVar::Var(var));
+            } else if wire < first_input + inputs.len() {
+                wires.push(match inputs[wire - first_input] {
+                    FpVar::Var(var) => lc!() + var.variable,
+                    FpVar::Constant(c) => lc!() + (*c, Variable::One),
+                });
+            } else {
+                wires.push(lc!() + cs.new_witness_variable(|| value(wire))?);
+            }
+        }
+
+        let to_lc = |terms: &LinearTerms<F>| {
+            terms
+                .iter()
+                .fold(lc!(), |acc, (wire, coeff)| acc + (*coeff, &wires[*wire]))
+        };
+        for (a, b, c) in self.solver.r1cs.constraints.iter() {
+            cs.enforce_constraint(to_lc(a), to_lc(b), to_lc(c))?;
+        }
+        Ok(z_i1)
+    }
+}